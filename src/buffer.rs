@@ -21,6 +21,7 @@
 /// C source: buffer.c (18,256 bytes) - IMMUTABLE REFERENCE
 
 use std::collections::VecDeque;
+use std::time::SystemTime;
 use crate::error::EdError;
 
 /// Safety limits to prevent resource exhaustion (matching GNU ed behavior)
@@ -29,6 +30,8 @@ pub struct SafetyLimits {
     pub max_line_length: usize,      // Default: 1MB  
     pub max_buffer_lines: usize,     // Default: 10M lines
     pub max_recursion_depth: usize,  // Default: 1000
+    pub max_command_length: usize,   // Default: 1MB
+    pub max_undo_bytes: usize,       // Default: 256MB of undo text per command
 }
 
 impl Default for SafetyLimits {
@@ -38,6 +41,8 @@ impl Default for SafetyLimits {
             max_line_length: 1024 * 1024,           // 1MB
             max_buffer_lines: 10_000_000,           // 10M lines
             max_recursion_depth: 1000,              // 1000 levels
+            max_command_length: 1024 * 1024,        // 1MB
+            max_undo_bytes: 256 * 1024 * 1024,      // 256MB
         }
     }
 }
@@ -50,16 +55,27 @@ enum UndoOperation {
     ModifyLine { position: usize, old_line: String, new_line: String },
 }
 
+impl UndoOperation {
+    /// text_len - bytes of line text the record keeps alive
+    fn text_len(&self) -> usize {
+        match self {
+            UndoOperation::AddLine { line, .. } | UndoOperation::DeleteLine { line, .. } => line.len(),
+            UndoOperation::ModifyLine { old_line, new_line, .. } => old_line.len() + new_line.len(),
+        }
+    }
+}
+
 /// Line buffer with GNU ed semantics and Rust memory safety
 pub struct EdBuffer {
     lines: VecDeque<String>,
     current_addr_: usize,           // matches C current_addr_ exactly
     last_addr_: usize,              // matches C last_addr_ exactly
-    modified_: u8,                  // matches C modified_ (bitfield for warned)
+    modified_: u8,                  // matches C modified_: 1 = modified | 2 = warned
     isbinary_: bool,               // matches C isbinary_ exactly
     pub filename: Option<String>,
     limits: SafetyLimits,
     undo_stack: Vec<UndoOperation>,
+    undo_bytes: usize,             // memory held by undo_stack, see push_undo_atom
     yank_buffer: Vec<String>,      // GNU ed yank buffer
     marks: [Option<usize>; 26],    // line markers - matches C mark[26] exactly
     markno: usize,                 // line marker count - matches C markno exactly
@@ -67,6 +83,10 @@ pub struct EdBuffer {
     u_current_addr: i32,           // matches C u_current_addr (-1 if undo disabled)
     u_last_addr: i32,              // matches C u_last_addr (-1 if undo disabled)
     u_modified: bool,              // matches C u_modified
+    u_marks: [Option<usize>; 26],  // marks 'u' restores (GNU ed marks follow the relinked nodes)
+    u_unterminated_line: Option<usize>,  // likewise for io.c unterminated_line
+    // rust-ed --safe-write: file last loaded or written, with its mtime and size
+    disk_state: Option<(String, SystemTime, u64)>,
 }
 
 impl EdBuffer {
@@ -81,6 +101,7 @@ impl EdBuffer {
             filename: None,
             limits: SafetyLimits::default(),
             undo_stack: Vec::new(),
+            undo_bytes: 0,
             yank_buffer: Vec::new(),
             marks: [None; 26],
             markno: 0,
@@ -88,9 +109,24 @@ impl EdBuffer {
             u_current_addr: -1,  // disabled initially
             u_last_addr: -1,     // disabled initially
             u_modified: false,
+            u_marks: [None; 26],
+            u_unterminated_line: None,
+            disk_state: None,
         }
     }
+
+    /// init_buffers with caller-supplied safety limits (not in C original)
+    pub fn with_limits(limits: SafetyLimits) -> Self {
+        let mut buffer = Self::new();
+        buffer.limits = limits;
+        buffer
+    }
     
+    /// limits - the safety limits this buffer was created with (not in C original)
+    pub fn limits(&self) -> &SafetyLimits {
+        &self.limits
+    }
+
     /// current_addr - matches buffer.c:42  
     // NOTE: This should be moved to io.rs to match C source structure
     // Keeping temporary access method until migration is complete
@@ -127,6 +163,9 @@ impl EdBuffer {
     }
     
     /// modified - matches buffer.c:53 (ignore warned bit)
+    /// The warned bit records that q or e already warned about unsaved
+    /// changes; it only counts together with the modified bit, and every
+    /// change to the buffer (modified_ = 1) clears it so the next q warns again
     pub fn modified(&self) -> bool {
         (self.modified_ & 1) != 0
     }
@@ -172,7 +211,7 @@ impl EdBuffer {
     // Note: link_nodes, insert_node, add_line_node abstracted by VecDeque
     
     /// too_many_lines - matches buffer.c:81
-    fn too_many_lines(&self) -> bool {
+    pub fn too_many_lines(&self) -> bool {
         self.lines.len() >= self.limits.max_buffer_lines
     }
     
@@ -185,22 +224,23 @@ impl EdBuffer {
         }
 
         // Insert at specified address and create undo records (GNU ed logic)
-        let mut insert_pos = addr;
+        // The last appended line becomes current, or addr if nothing was added
+        self.current_addr_ = addr;
         for line in lines_to_add {
-            self.lines.insert(insert_pos, line.clone());
+            let insert_pos = self.current_addr_;
+            self.put_sbuf_line(line)?;
 
             // Create undo record for each added line (matches GNU ed push_undo_atom)
-            self.undo_stack.push(UndoOperation::AddLine {
+            self.push_undo_atom(UndoOperation::AddLine {
                 position: insert_pos,
                 line: line.clone(),
             });
-
-            insert_pos += 1;
         }
 
-        self.last_addr_ = self.lines.len();
-        self.current_addr_ = insert_pos.saturating_sub(1);
-        self.modified_ = 1;
+        // An empty append (e.g. 'a' followed by '.') leaves the buffer unmodified
+        if !lines_to_add.is_empty() {
+            self.modified_ = 1;
+        }
         Ok(true)
     }
     
@@ -209,9 +249,12 @@ impl EdBuffer {
         self.yank_buffer.clear();
     }
     
-    /// close_sbuf - matches buffer.c:171 (string buffer operations)
-    pub fn close_sbuf(&self) -> bool {
-        // TODO: Implement string buffer closing
+    /// close_sbuf - matches buffer.c:171
+    /// rust-ed keeps line text in `lines` instead of a scratch file, so closing
+    /// only drops what refers to the old text: the yank buffer and undo stack
+    pub fn close_sbuf(&mut self) -> bool {
+        self.clear_yank_buffer();
+        self.clear_undo_stack();
         true
     }
     
@@ -237,27 +280,25 @@ impl EdBuffer {
         }
 
         // Copy lines following GNU ed algorithm (lines 205-221)
+        // Sources before the destination stay put as copies are inserted; sources
+        // after it (all of them for a copy upwards, or the second part's) are
+        // shifted by one by each copy
         let mut source_addr = first_addr;
+        let mut source_step = if addr < first_addr { 2 } else { 1 };
         loop {
             for _ in 0..n {
-                if self.too_many_lines() {
-                    return Err(EdError::InvalidCommand);
-                }
-
                 // Get the line to copy (GNU ed dup_line_node)
                 // source_addr is 1-based, get_line expects 1-based
                 if let Some(line_content) = self.get_line(source_addr) {
                     let line_to_copy = line_content.to_string();
 
-                    // Insert after current_addr (GNU ed add_line_node behavior)
-                    // current_addr is 1-based, Vec::insert expects 0-based
-                    let insert_pos = self.current_addr_;  // Insert after this position (0-based for Vec)
-                    self.lines.insert(insert_pos, line_to_copy.clone());
-                    self.last_addr_ = self.lines.len();
-                    self.current_addr_ += 1;  // Increment to point to newly inserted line
+                    // Insert after current_addr, which moves to the copy
+                    // (GNU ed add_line_node behavior)
+                    let insert_pos = self.current_addr_;
+                    self.put_sbuf_line(&line_to_copy)?;
 
                     // Record undo operation (GNU ed push_undo_atom)
-                    self.undo_stack.push(UndoOperation::AddLine {
+                    self.push_undo_atom(UndoOperation::AddLine {
                         position: insert_pos,
                         line: line_to_copy
                     });
@@ -265,14 +306,15 @@ impl EdBuffer {
                     return Err(EdError::InvalidAddress);
                 }
 
-                source_addr += 1;
+                source_addr += source_step;
             }
 
             // Check if we need to continue with second part (GNU ed algorithm)
             if m > 0 {
                 n = m;
                 m = 0;
-                source_addr = self.current_addr_ - n + 1;  // Adjust for newly inserted lines
+                source_addr = self.current_addr_ + 1;  // GNU ed: search_line_node( current_addr_ + 1 )
+                source_step = 2;
             } else {
                 break;
             }
@@ -288,17 +330,22 @@ impl EdBuffer {
         if from > self.last_addr_ || to > self.last_addr_ || from > to {
             return Err(EdError::InvalidAddress);
         }
-        
+
+        // The deleted lines replace the yank buffer (GNU ed buffer.c:229)
+        self.yank_lines(from, to)?;
+
         // Record undo operations before deletion and unmark lines (GNU ed unmark_line_node)
-        for line_num in from..=to {
+        // Last line first, so undo (which pops) puts them back from the top down
+        for line_num in (from..=to).rev() {
             if let Some(line) = self.get_line(line_num) {
-                self.undo_stack.push(UndoOperation::DeleteLine {
+                self.push_undo_atom(UndoOperation::DeleteLine {
                     position: line_num - 1,
                     line: line.to_string()
                 });
             }
             // Unmark any marks pointing to this line (GNU ed main_loop.c:101)
             self.unmark_line_node(line_num);
+            crate::io::unmark_unterminated_line(line_num);
         }
         
         // Delete lines in reverse order to maintain indices
@@ -307,19 +354,20 @@ impl EdBuffer {
                 self.lines.remove(line_num - 1);
             }
         }
+        crate::global::unset_active_lines(from, to);
 
         self.last_addr_ = self.lines.len();
 
         // Adjust marks that point to lines after the deleted range (GNU ed behavior)
         let lines_deleted = to - from + 1;
-        for i in 0..26 {
-            if let Some(marked_line) = self.marks[i] {
-                if marked_line > to {
-                    // Adjust mark to point to new line number after deletion
-                    self.marks[i] = Some(marked_line - lines_deleted);
-                }
-            }
-        }
+        let adjust = |marked_line: &mut usize| if *marked_line > to {
+            // Adjust mark to point to new line number after deletion
+            *marked_line -= lines_deleted;
+        };
+        self.marks.iter_mut().flatten().for_each(adjust);
+        crate::session::with(|s| s.unterminated_line.iter_mut().for_each(adjust));
+
+        self.debug_check_markno();
 
         // Update current address (GNU ed buffer.c:239)
         // current_addr_ = min( from, last_addr_ );
@@ -358,13 +406,22 @@ impl EdBuffer {
         // Add final newline to joined result (GNU ed adds '\n' to final buffer)
         joined.push('\n');
 
-        // Delete the lines from from+1 to to (GNU ed delete_lines logic)
+        // Joining through an unterminated last line leaves the result unterminated
+        let unterminated = to == self.last_addr_ && crate::io::unterminated_last_line(self);
+
+        // Delete the lines from from+1 to to (GNU ed delete_lines logic); the
+        // yank buffer gets all the joined lines, as GNU ed deletes from..=to
+        let yanked: Vec<String> = self.lines.range(from - 1..to).cloned().collect();
         if to > from {
             self.delete_lines(from + 1, to, isglobal)?;
         }
+        self.yank_buffer = yanked;
 
         // Replace the first line with joined content (GNU ed put_sbuf_line + push_undo_atom)
         self.modify_line(from, joined.trim_end_matches('\n').to_string())?;
+        if unterminated {
+            crate::io::mark_unterminated_line(from);
+        }
 
         // Set current address to the joined line (GNU ed: current_addr_ = from - 1; but then += 1)
         self.current_addr_ = from;
@@ -387,12 +444,16 @@ impl EdBuffer {
         // addr == first_addr - 1: moving to just before the range (no change)
         // addr == second_addr: moving to just after the range (no change)
         if addr == first_addr.saturating_sub(1) || addr == second_addr {
-            // Set current address and return (GNU ed behavior)
+            // Set current address and return; the buffer still counts as
+            // modified (GNU ed buffer.c:379 sets modified_ on every path)
             self.current_addr_ = second_addr;
+            self.modified_ = 1;
             return Ok(true);
         }
 
         // Extract the lines to be moved (0-based indexing for Vec operations)
+        crate::global::move_active_lines(first_addr, second_addr, addr);
+        self.move_marks(first_addr, second_addr, addr);
         let mut moved_lines = Vec::new();
         for line_num in first_addr..=second_addr {
             if let Some(line) = self.get_line(line_num) {
@@ -401,9 +462,11 @@ impl EdBuffer {
         }
 
         // Remove the lines from their original position (in reverse order to maintain indices)
+        // Each step is recorded so 'u' can undo the move (GNU ed pushes UMOV atoms)
         for line_num in (first_addr..=second_addr).rev() {
             if line_num > 0 && line_num <= self.lines.len() {
-                self.lines.remove(line_num - 1); // Convert to 0-based
+                let line = self.lines.remove(line_num - 1).unwrap_or_default(); // Convert to 0-based
+                self.push_undo_atom(UndoOperation::DeleteLine { position: line_num - 1, line });
             }
         }
 
@@ -424,6 +487,7 @@ impl EdBuffer {
         for (i, line) in moved_lines.iter().enumerate() {
             if insert_index + i <= self.lines.len() {
                 self.lines.insert(insert_index + i, line.clone());
+                self.push_undo_atom(UndoOperation::AddLine { position: insert_index + i, line: line.clone() });
             }
         }
 
@@ -450,15 +514,21 @@ impl EdBuffer {
     }
     
     /// open_sbuf - matches buffer.c:386
+    /// A new scratch store starts out as text with every line terminated
     pub fn open_sbuf(&mut self) -> bool {
-        // TODO: Implement string buffer opening
+        self.isbinary_ = false;
+        crate::io::reset_unterminated_line();
         true
     }
     
     /// path_max - matches buffer.c:400
-    pub fn path_max(_filename: &str) -> i32 {
-        // TODO: Implement path length checking
-        4096 // Default path max
+    pub fn path_max(filename: Option<&str>) -> i32 {
+        use nix::unistd::{pathconf, PathconfVar};
+        match pathconf(filename.unwrap_or("/"), PathconfVar::PATH_MAX) {
+            Ok(Some(result)) => result.max(256) as i32,
+            Ok(None) => 1024,
+            Err(_) => 256,
+        }
     }
     
     /// put_lines - matches buffer.c:412
@@ -477,21 +547,15 @@ impl EdBuffer {
         self.current_addr_ = addr;
 
         // Insert each line from yank buffer after current address (GNU ed lines 420-436)
-        for line in self.yank_buffer.iter() {
-            if self.too_many_lines() {
-                return Err(EdError::InvalidCommand);
-            }
-
-            // Insert after current_addr (0-based for Vec::insert)
+        for line in self.yank_buffer.clone() {
+            // Insert after current_addr, which moves to the new line
             let insert_pos = self.current_addr_;
-            self.lines.insert(insert_pos, line.clone());
-            self.last_addr_ = self.lines.len();
-            self.current_addr_ += 1; // Move to newly inserted line
+            self.put_sbuf_line(&line)?;
 
             // Record undo operation (GNU ed push_undo_atom)
-            self.undo_stack.push(UndoOperation::AddLine {
+            self.push_undo_atom(UndoOperation::AddLine {
                 position: insert_pos,
-                line: line.clone(),
+                line,
             });
         }
 
@@ -502,9 +566,22 @@ impl EdBuffer {
     }
     
     /// put_sbuf_line - matches buffer.c:446
-    pub fn put_sbuf_line(&self, buf: &str, _size: usize) -> Option<String> {
-        // TODO: Implement string buffer line output
-        Some(buf.to_string())
+    /// Add the text of buf up to its first newline (or all of it) as a new line
+    /// after the current address, which then moves to it. Returns the rest of
+    /// buf after the newline. Like GNU ed, the caller records the undo atom.
+    pub fn put_sbuf_line<'a>(&mut self, buf: &'a str) -> Result<&'a str, EdError> {
+        let (text, rest) = buf.split_once('\n').unwrap_or((buf, ""));
+        if self.too_many_lines() {
+            return Err(EdError::TooManyLines);
+        }
+
+        let addr = self.current_addr_;
+        self.lines.insert(addr, text.to_string());
+        crate::global::shift_active_lines(addr + 1, 1);
+        self.shift_marks(addr + 1);
+        self.last_addr_ = self.lines.len();
+        self.current_addr_ = addr + 1;
+        Ok(rest)
     }
     
     /// search_line_node - matches buffer.c:483
@@ -535,13 +612,29 @@ impl EdBuffer {
         Ok(true)
     }
     
+    /// clear_undo_unless_global - GNU ed's `if( !isglobal ) clear_undo_stack();`
+    /// run before each buffer-changing command (not in C original). Commands
+    /// run by g/v/G/V keep adding to the stack the global command cleared, so
+    /// one 'u' reverts the whole global command.
+    pub fn clear_undo_unless_global(&mut self, isglobal: bool) {
+        if !isglobal {
+            self.clear_undo_stack();
+        }
+    }
+
     /// clear_undo_stack - matches buffer.c:538
+    /// Starts the undo group of a command: through clear_undo_unless_global
+    /// before each buffer-changing command, and before a global command list.
+    /// close_sbuf and reset_undo_state drop the undo history through it too.
     pub fn clear_undo_stack(&mut self) {
         self.undo_stack.clear();
+        self.undo_bytes = 0;
         // Save current state for undo (matches buffer.c:555-557)
         self.u_current_addr = self.current_addr_ as i32;
         self.u_last_addr = self.last_addr_ as i32;
         self.u_modified = self.modified();
+        self.u_marks = self.marks;
+        self.u_unterminated_line = crate::session::with(|s| s.unterminated_line);
     }
     
     /// reset_undo_state - matches buffer.c:561
@@ -556,12 +649,21 @@ impl EdBuffer {
     // Note: free_undo_stack handled by Rust's Drop trait
     
     /// push_undo_atom - matches buffer.c:583 (internal)
-    fn push_undo_atom(&mut self, op_type: i32, from: usize, _to: usize, line: String) {
-        match op_type {
-            1 => self.undo_stack.push(UndoOperation::AddLine { position: from, line }),
-            2 => self.undo_stack.push(UndoOperation::DeleteLine { position: from, line }),
-            _ => {} // Other types as needed
+    /// Once the atoms of a command outgrow max_undo_bytes the command goes on
+    /// without undo, as it does in GNU ed when the undo stack cannot grow:
+    /// reset_undo_state drops the atoms, and until the next command starts
+    /// nothing more is recorded and 'u' reports "Nothing to undo".
+    fn push_undo_atom(&mut self, op: UndoOperation) {
+        if self.u_current_addr < 0 {
+            return;
         }
+        let size = std::mem::size_of::<UndoOperation>() + op.text_len();
+        if self.undo_bytes + size > self.limits.max_undo_bytes {
+            self.reset_undo_state();
+            return;
+        }
+        self.undo_bytes += size;
+        self.undo_stack.push(op);
     }
     
     /// undo - matches buffer.c:613
@@ -575,6 +677,8 @@ impl EdBuffer {
         let o_current_addr = self.current_addr_;
         let o_last_addr = self.last_addr_;
         let o_modified = self.modified();
+        let o_marks = self.marks;
+        let o_unterminated_line = crate::session::with(|s| s.unterminated_line);
 
         // Perform undo operations (GNU ed buffer.c:624-640)
         // GNU ed: for( n = u_len - 1; n >= 0; --n ) - undoes ALL operations
@@ -585,12 +689,14 @@ impl EdBuffer {
                     // Undo add: remove the added line
                     if position < self.lines.len() {
                         self.lines.remove(position);
+                        crate::global::unset_active_lines(position + 1, position + 1);
                     }
                 },
                 UndoOperation::DeleteLine { position, line } => {
                     // Undo delete: restore the deleted line
                     if position <= self.lines.len() {
-                        self.lines.insert(position, line);
+                        self.current_addr_ = position;
+                        self.put_sbuf_line(&line)?;
                     }
                 },
                 UndoOperation::ModifyLine { position, old_line, .. } => {
@@ -602,17 +708,26 @@ impl EdBuffer {
             }
         }
 
+        self.undo_bytes = 0;
+
         // Update buffer state after undoing all operations
         self.last_addr_ = self.lines.len();
 
         // Restore undo state (matches buffer.c:648-650)
         self.current_addr_ = self.u_current_addr as usize;
         self.modified_ = if self.u_modified { 1 } else { 0 };
+        // Lines come back where they were, and so do the marks on them
+        self.marks = self.u_marks;
+        self.markno = self.marks.iter().filter(|m| m.is_some()).count();
+        self.debug_check_markno();
+        crate::session::with(|s| s.unterminated_line = self.u_unterminated_line);
 
         // Update undo state for next undo (matches buffer.c:648-650)
         self.u_current_addr = o_current_addr as i32;
         self.u_last_addr = o_last_addr as i32;
         self.u_modified = o_modified;
+        self.u_marks = o_marks;
+        self.u_unterminated_line = o_unterminated_line;
 
         Ok(true)
     }
@@ -673,31 +788,92 @@ impl EdBuffer {
             }
         };
 
-        // Clear existing buffer
+        crate::io::reject_directory(filename, &file)?;
+
+        // Clear existing buffer (GNU ed main_loop.c:590-593: delete_lines,
+        // close_sbuf, open_sbuf)
+        if !self.lines.is_empty() {
+            crate::global::unset_active_lines(1, self.lines.len());
+        }
         self.lines.clear();
+        self.clear_marks();
         self.current_addr_ = 0;
         self.last_addr_ = 0;
+        self.close_sbuf();
+        self.open_sbuf();
 
         // Read file line by line
-        let reader = BufReader::new(file);
+        let mut reader = BufReader::new(file);
         let mut total_bytes = 0;
+        let mut newline_added = false;
 
-        for line_result in reader.lines() {
-            match line_result {
-                Ok(line) => {
-                    total_bytes += line.len() + 1; // +1 for newline
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(size) => {
+                    // Count the bytes as read, like read_stream (io.c:259)
+                    total_bytes += size;
+                    // GNU ed io.c:212 - a NUL byte makes the buffer binary
+                    if line.contains('\0') {
+                        self.isbinary_ = true;
+                    }
+                    if line.ends_with('\n') {
+                        line.pop();
+                        // io.c:214 - with --strip-trailing-cr the CR of a
+                        // CR/LF pair goes, and is not counted
+                        if crate::strip_cr() && line.ends_with('\r') {
+                            line.pop();
+                            total_bytes -= 1;
+                        }
+                    } else {
+                        crate::io::mark_unterminated_line(self.lines.len() + 1);
+                        newline_added = true;
+                    }
                     self.lines.push_back(line);
                 },
                 Err(_) => return Err(EdError::InvalidAddress),
             }
         }
 
+        // io.c:229 and io.c:271-272 - the newline appended to a text file
+        // is reported and counted; a binary file keeps its exact size
+        if newline_added && !self.isbinary_ {
+            total_bytes += 1;
+            if !crate::scripted() {
+                outln!("Newline appended");
+            }
+        }
+
         // Set current line to last line (GNU ed behavior)
         self.last_addr_ = self.lines.len();
         self.current_addr_ = self.last_addr_;
+        self.record_disk_state(filename);
 
         Ok(total_bytes)
     }
+
+    /// record_disk_state - remember a file's mtime and size after loading or
+    /// writing it, for the --safe-write check (not in C original)
+    pub fn record_disk_state(&mut self, filename: &str) {
+        self.disk_state = std::fs::metadata(filename).ok().and_then(|meta| {
+            let mtime = meta.modified().ok()?;
+            Some((filename.to_string(), mtime, meta.len()))
+        });
+    }
+
+    /// changed_on_disk - whether filename differs from the recorded mtime and
+    /// size; a file that was never recorded counts as unchanged (not in C original)
+    pub fn changed_on_disk(&self, filename: &str) -> bool {
+        match &self.disk_state {
+            Some((name, mtime, size)) if name == filename => {
+                std::fs::metadata(filename).is_ok_and(|meta| {
+                    meta.len() != *size || meta.modified().is_ok_and(|t| t != *mtime)
+                })
+            }
+            _ => false,
+        }
+    }
     
     /// Set filename
     pub fn set_filename(&mut self, filename: String) {
@@ -733,36 +909,24 @@ impl EdBuffer {
             return Err(EdError::InvalidAddress);
         }
 
-        // Insert the line
-        self.lines.insert(position, line.clone());
+        // Insert after position, which becomes the new line's address
+        self.current_addr_ = position;
+        self.put_sbuf_line(&line)?;
 
         // Create undo record for the inserted line (matches GNU ed push_undo_atom)
-        self.undo_stack.push(UndoOperation::AddLine {
-            position,
-            line: line.clone(),
-        });
-
-        self.last_addr_ = self.lines.len();
-        self.current_addr_ = position + 1; // Set current to newly inserted line (1-based)
+        self.push_undo_atom(UndoOperation::AddLine { position, line });
         self.modified_ = 1;
 
-        // Adjust marks that point to lines at or after the insertion point (GNU ed behavior)
-        let insert_line_1based = position + 1; // Convert to 1-based addressing
-        for i in 0..26 {
-            if let Some(marked_line) = self.marks[i] {
-                if marked_line >= insert_line_1based {
-                    // Adjust mark to point to new line number after insertion
-                    self.marks[i] = Some(marked_line + 1);
-                }
-            }
-        }
-
         Ok(())
     }
     
     /// Clear buffer (for 'e' command)
     pub fn clear_buffer(&mut self) {
+        if !self.lines.is_empty() {
+            crate::global::unset_active_lines(1, self.lines.len());
+        }
         self.lines.clear();
+        self.clear_marks();
         self.current_addr_ = 0;
         self.last_addr_ = 0;
         self.modified_ = 0;
@@ -793,15 +957,17 @@ impl EdBuffer {
 
         // Record undo operation before modifying
         if let Some(old_line) = self.lines.get(line_num - 1) {
-            self.undo_stack.push(UndoOperation::ModifyLine {
+            self.push_undo_atom(UndoOperation::ModifyLine {
                 position: line_num - 1,
                 old_line: old_line.clone(),
                 new_line: new_content.clone(),
             });
         }
 
-        // Actually modify the line
+        // Actually modify the line; the new text ends in '\n', as GNU ed's
+        // new node is not the unterminated one
         self.lines[line_num - 1] = new_content;
+        crate::io::unmark_unterminated_line(line_num);
         self.modified_ = 1;
         self.current_addr_ = line_num;
 
@@ -816,7 +982,7 @@ impl EdBuffer {
         // Convert character to index (GNU ed logic: c -= 'a')
         let index = (c as u8).wrapping_sub(b'a') as usize;
         if index >= 26 {
-            return Err(EdError::InvalidCommand); // Invalid mark character
+            return Err(EdError::InvalidMarkCharacter);
         }
 
         // Validate line address
@@ -831,6 +997,7 @@ impl EdBuffer {
 
         // Set the mark to point to this line (GNU ed: mark[c] = lp)
         self.marks[index] = Some(line_addr);
+        self.debug_check_markno();
 
         Ok(())
     }
@@ -844,6 +1011,48 @@ impl EdBuffer {
                 self.markno -= 1;
             }
         }
+        self.debug_check_markno();
+    }
+
+    /// A line is being inserted at line from: marks on it and on the lines
+    /// after it, and the unterminated line, move down with them (not in C original)
+    fn shift_marks(&mut self, from: usize) {
+        let shift = |mark: &mut usize| if *mark >= from {
+            *mark += 1;
+        };
+        self.marks.iter_mut().flatten().for_each(shift);
+        crate::session::with(|s| s.unterminated_line.iter_mut().for_each(shift));
+    }
+
+    /// Lines first..=second are being moved after addr: marks and the
+    /// unterminated line follow their lines, like move_active_lines (not in C original)
+    fn move_marks(&mut self, first: usize, second: usize, addr: usize) {
+        let n = second - first + 1;
+        let follow = |mark: &mut usize| {
+            let line = *mark;
+            *mark = if (first..=second).contains(&line) {
+                if addr < first { line - first + addr + 1 } else { line - first + addr + 1 - n }
+            } else if addr < first && line > addr && line < first {
+                line + n
+            } else if addr > second && line > second && line <= addr {
+                line - n
+            } else {
+                line
+            };
+        };
+        self.marks.iter_mut().flatten().for_each(follow);
+        crate::session::with(|s| s.unterminated_line.iter_mut().for_each(follow));
+    }
+
+    /// Drop every mark when the whole buffer is replaced (GNU ed frees the marked nodes)
+    fn clear_marks(&mut self) {
+        self.marks = [None; 26];
+        self.markno = 0;
+    }
+
+    /// markno must always equal the number of set marks
+    fn debug_check_markno(&self) {
+        debug_assert_eq!(self.markno, self.marks.iter().filter(|m| m.is_some()).count());
     }
 
     /// get_marked_node_addr - matches main_loop.c:111
@@ -852,7 +1061,7 @@ impl EdBuffer {
         // Convert character to index (GNU ed logic: c -= 'a')
         let index = (c as u8).wrapping_sub(b'a') as usize;
         if index >= 26 {
-            return Err(EdError::InvalidCommand); // Invalid mark character
+            return Err(EdError::InvalidMarkCharacter);
         }
 
         // Return the marked line address, or error if not set
@@ -861,4 +1070,156 @@ impl EdBuffer {
             None => Err(EdError::InvalidAddress), // Mark not set or line deleted
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_marks(buffer: &EdBuffer) -> usize {
+        buffer.marks.iter().filter(|m| m.is_some()).count()
+    }
+
+    #[test]
+    fn markno_tracks_mark_delete_mark_cycles() {
+        let mut buffer = EdBuffer::new();
+        let lines: Vec<String> = (1..=6).map(|n| format!("line {}", n)).collect();
+        buffer.append_lines(&lines, 0).unwrap();
+
+        buffer.mark_line_node(2, 'a').unwrap();
+        buffer.mark_line_node(4, 'b').unwrap();
+        buffer.mark_line_node(4, 'c').unwrap();
+        buffer.mark_line_node(5, 'a').unwrap();  // re-mark an already set slot
+        assert_eq!(buffer.markno, 3);
+
+        buffer.delete_lines(4, 4, false).unwrap();
+        assert_eq!(buffer.markno, 1);
+        assert_eq!(buffer.get_marked_node_addr('a').unwrap(), 4);
+
+        buffer.insert_line(0, "new first".to_string()).unwrap();
+        buffer.append_lines(&["after a".to_string()], 2).unwrap();
+        assert_eq!(buffer.get_marked_node_addr('a').unwrap(), 6);
+
+        buffer.mark_line_node(1, 'b').unwrap();
+        buffer.delete_lines(1, 6, false).unwrap();
+        assert_eq!(buffer.markno, 0);
+        assert_eq!(buffer.markno, set_marks(&buffer));
+
+        buffer.mark_line_node(1, 'z').unwrap();
+        buffer.clear_buffer();
+        assert_eq!(buffer.markno, 0);
+        assert_eq!(buffer.markno, set_marks(&buffer));
+    }
+
+    #[test]
+    fn put_sbuf_line_adds_lines_after_current() {
+        let mut buffer = EdBuffer::new();
+        let rest = buffer.put_sbuf_line("first\nthird\n").unwrap();
+        assert_eq!(rest, "third\n");
+        assert_eq!(buffer.put_sbuf_line(rest).unwrap(), "");
+        buffer.mark_line_node(2, 'a').unwrap();
+
+        buffer.set_current_line(1).unwrap();
+        assert_eq!(buffer.put_sbuf_line("second").unwrap(), "");
+
+        assert_eq!(buffer.current_line(), 2);
+        assert_eq!(buffer.last_addr(), 3);
+        assert_eq!(buffer.get_sbuf_line(1), Some("first"));
+        assert_eq!(buffer.get_sbuf_line(2), Some("second"));
+        assert_eq!(buffer.get_sbuf_line(3), Some("third"));
+        assert_eq!(buffer.get_marked_node_addr('a').unwrap(), 3);
+    }
+
+    #[test]
+    fn close_and_open_sbuf_reset_scratch_state() {
+        let mut buffer = EdBuffer::new();
+        buffer.append_lines(&["one".to_string(), "two".to_string()], 0).unwrap();
+        buffer.yank_lines(1, 2).unwrap();
+        buffer.set_binary();
+
+        assert!(buffer.close_sbuf());
+        assert!(buffer.open_sbuf());
+        assert!(!buffer.isbinary());
+        assert!(buffer.put_lines(0).is_err());
+        assert!(EdBuffer::path_max(None) >= 256);
+    }
+
+    #[test]
+    fn append_lines_empty_slice_leaves_buffer_unmodified() {
+        let mut buffer = EdBuffer::new();
+        buffer.append_lines(&[], 0).unwrap();
+        assert!(!buffer.modified());
+
+        buffer.append_lines(&["line".to_string()], 0).unwrap();
+        buffer.set_modified(false);
+        buffer.append_lines(&[], 1).unwrap();
+        assert!(!buffer.modified());
+    }
+
+    #[test]
+    fn modified_and_warned_bits_follow_gnu_ed() {
+        let mut buffer = EdBuffer::new();
+        assert!(!buffer.modified() && !buffer.warned());
+
+        // Warned without modified is not warned (modified_ == 2)
+        buffer.set_warned(true);
+        assert!(!buffer.modified() && !buffer.warned());
+        buffer.set_modified(true);
+        assert!(buffer.modified() && !buffer.warned());
+
+        buffer.set_warned(true);
+        assert!(buffer.modified() && buffer.warned());
+        buffer.set_warned(false);
+        assert!(buffer.modified() && !buffer.warned());
+
+        // set_modified clears the warned bit either way
+        buffer.set_warned(true);
+        buffer.set_modified(true);
+        assert!(buffer.modified() && !buffer.warned());
+        buffer.set_warned(true);
+        buffer.set_modified(false);
+        assert!(!buffer.modified() && !buffer.warned());
+        buffer.set_modified(true);
+        assert!(!buffer.warned());
+
+        // Changing the buffer after a warning means the next q warns again
+        buffer.set_warned(true);
+        buffer.append_lines(&["line".to_string()], 0).unwrap();
+        assert!(buffer.modified() && !buffer.warned());
+        buffer.set_warned(true);
+        buffer.delete_lines(1, 1, false).unwrap();
+        assert!(buffer.modified() && !buffer.warned());
+    }
+
+    #[test]
+    fn undo_is_dropped_once_a_command_exceeds_the_budget() {
+        let mut buffer = EdBuffer::with_limits(SafetyLimits {
+            max_undo_bytes: 4 * std::mem::size_of::<UndoOperation>() + 64,
+            ..SafetyLimits::default()
+        });
+        buffer.clear_undo_stack();
+        for n in 1..=8 {
+            buffer.append_line(format!("line {}", n));
+        }
+
+        // A small command keeps its undo
+        buffer.clear_undo_stack();
+        buffer.modify_line(1, "first".to_string()).unwrap();
+        buffer.undo(false).unwrap();
+        assert_eq!(buffer.get_line(1), Some("line 1"));
+
+        // One that changes every line does not, but still runs to the end
+        buffer.clear_undo_stack();
+        for n in 1..=8 {
+            buffer.modify_line(n, format!("changed {}", n)).unwrap();
+        }
+        assert_eq!(buffer.get_line(8), Some("changed 8"));
+        assert!(matches!(buffer.undo(false), Err(EdError::NothingToUndo)));
+
+        // The next command starts with undo again
+        buffer.clear_undo_stack();
+        buffer.delete_lines(1, 1, false).unwrap();
+        buffer.undo(false).unwrap();
+        assert_eq!(buffer.get_line(1), Some("changed 1"));
+    }
+}
@@ -23,12 +23,11 @@
 // Vec is in std::vec, not std::collections
 
 /// Argument parsing option types - matches carg_parser.h ap_Has_arg enum
+/// ap_maybe and ap_yme are left out: no ed option takes an optional argument
 #[derive(Clone, Copy, PartialEq)]
 pub enum ApHasArg {
     ApNo = 0,      // Option has no argument
     ApYes = 1,     // Option requires an argument
-    ApMaybe = 2,   // Option has optional argument
-    ApYesme = 3,   // Option requires argument and merges
 }
 
 /// Option specification - matches carg_parser.h ap_Option struct
@@ -179,10 +178,10 @@ fn parse_long_option(
                         }
                         *argindp += 1;
                     }
-                    ApHasArg::ApYes | ApHasArg::ApYesme => {
+                    ApHasArg::ApYes => {
                         let arg_to_use = embedded_arg.or(arg);
                         if let Some(argument) = arg_to_use {
-                            if option.has_arg == ApHasArg::ApYes && argument.is_empty() {
+                            if argument.is_empty() {
                                 return set_error(ap, "option requires an argument -- '", option_name, "'");
                             }
                             if !set_argument(ap, argument) {
@@ -196,14 +195,6 @@ fn parse_long_option(
                         }
                         *argindp += 1;
                     }
-                    ApHasArg::ApMaybe => {
-                        if let Some(argument) = embedded_arg {
-                            if !set_argument(ap, argument) {
-                                return false;
-                            }
-                        }
-                        *argindp += 1;
-                    }
                 }
                 return true;
             }
@@ -235,7 +226,7 @@ fn parse_short_option(
         // Find matching option
         let mut found = false;
         for option in options {
-            if option.code == code && option.name.is_none() {
+            if option.code == code {
                 found = true;
                 if !push_back_option(ap, code, None) {
                     return false;
@@ -246,7 +237,7 @@ fn parse_short_option(
                     ApHasArg::ApNo => {
                         cind += 1;
                     }
-                    ApHasArg::ApYes | ApHasArg::ApYesme => {
+                    ApHasArg::ApYes => {
                         // If there are more characters, use them as argument
                         if cind + 1 < opt_chars.len() {
                             let argument: String = opt_chars[cind + 1..].iter().collect();
@@ -256,7 +247,7 @@ fn parse_short_option(
                             *argindp += 1;
                             return true; // Done with this option string
                         } else if let Some(argument) = arg {
-                            if option.has_arg == ApHasArg::ApYes && argument.is_empty() {
+                            if argument.is_empty() {
                                 return set_error(ap, "option requires an argument -- '", &ch.to_string(), "'");
                             }
                             if !set_argument(ap, argument) {
@@ -269,17 +260,6 @@ fn parse_short_option(
                             return set_error(ap, "option requires an argument -- '", &ch.to_string(), "'");
                         }
                     }
-                    ApHasArg::ApMaybe => {
-                        if cind + 1 < opt_chars.len() {
-                            let argument: String = opt_chars[cind + 1..].iter().collect();
-                            if !set_argument(ap, &argument) {
-                                return false;
-                            }
-                            *argindp += 1;
-                            return true;
-                        }
-                        cind += 1;
-                    }
                 }
                 break;
             }
@@ -328,13 +308,15 @@ pub fn ap_init(
                 break;
             } else if arg.starts_with("--") {
                 // Long option
-                if !parse_long_option(ap, arg, next_arg, options, &mut argind) {
+                // A bad option sets ap.error (reported below); anything else is fatal
+                if !parse_long_option(ap, arg, next_arg, options, &mut argind) && ap.error.is_none() {
                     free_data(ap);
                     return false;
                 }
             } else {
                 // Short option(s)
-                if !parse_short_option(ap, arg, next_arg, options, &mut argind) {
+                // A bad option sets ap.error (reported below); anything else is fatal
+                if !parse_short_option(ap, arg, next_arg, options, &mut argind) && ap.error.is_none() {
                     free_data(ap);
                     return false;
                 }
@@ -26,6 +26,9 @@ pub enum EdError {
     #[error("?")]
     InvalidAddress,
 
+    #[error("?")]  // GNU ed: Unexpected address (main_loop.c unexpected_address)
+    UnexpectedAddress,
+
     #[error("?")]
     InvalidFilename,
 
@@ -41,12 +44,87 @@ pub enum EdError {
     #[error("?")]  // GNU ed: Nothing to put error (empty yank buffer)
     NothingToPut,
 
-    #[error("?")]  // GNU ed: Pattern not found in search
-    PatternNotFound,
+    #[error("?")]  // GNU ed: No previous pattern (empty RE with no earlier search)
+    NoPreviousPattern,
 
     #[error("?")]  // GNU ed: Warning - buffer modified (first quit attempt)
     WarningUnsavedChanges,
 
+    #[error("?")]  // GNU ed: Too many lines in buffer (buffer.c too_many_lines)
+    TooManyLines,
+
+    #[error("?")]  // GNU ed: Invalid command suffix (main_loop.c get_command_suffix)
+    InvalidCommandSuffix,
+
+    #[error("?")]  // GNU ed: Unexpected command suffix (main_loop.c unexpected_command_suffix)
+    UnexpectedCommandSuffix,
+
+    #[error("?")]  // GNU ed: Unknown command (main_loop.c exec_command)
+    UnknownCommand,
+
+    #[error("?")]  // GNU ed: Cannot open output file (io.c write_file)
+    CannotOpenOutput,
+
+    #[error("?")]  // GNU ed: Cannot write file (io.c write_stream)
+    CannotWriteFile,
+
+    #[error("?")]  // GNU ed: Cannot close output file (io.c write_file)
+    CannotCloseOutput,
+
+    #[error("?")]  // GNU ed: bad regular expression, carries the regex.c message
+    InvalidPattern(&'static str),
+
+    #[error("?")]  // GNU ed: Invalid destination (main_loop.c case 'm')
+    InvalidDestination,
+
+    #[error("?")]  // GNU ed: Cannot read input file (io.c read_stream_line)
+    CannotReadInput,
+
+    #[error("?")]  // GNU ed: Filename too long (main_loop.c get_filename)
+    FilenameTooLong,
+
+    #[error("?")]  // GNU ed: Number out of range (main_loop.c parse_int)
+    NumberOutOfRange,
+
+    #[error("?")]  // GNU ed: No previous command (main_loop.c get_shell_command)
+    NoPreviousCommand,
+
+    #[error("?")]  // GNU ed: No current filename (main_loop.c get_shell_command)
+    NoCurrentFilename,
+
+    #[error("?")]  // GNU ed: Shell access restricted (main_loop.c get_shell_command)
+    ShellRestricted,
+
+    #[error("?")]  // GNU ed: Directory access restricted (main.c may_access_filename)
+    DirectoryRestricted,
+
+    #[error("?")]  // GNU ed: Can't create shell process (main_loop.c command_shell)
+    CannotCreateShell,
+
+    #[error("?")]  // GNU ed: Cannot nest global commands (main_loop.c exec_command)
+    CannotNestGlobal,
+
+    #[error("?")]  // GNU ed: Invalid mark character (main_loop.c get_marked_node_addr)
+    InvalidMarkCharacter,
+
+    #[error("?")]  // GNU ed: Line too long (signal.c resize_buffer)
+    LineTooLong,
+
+    #[error("?")]  // GNU ed: Infinite substitution loop (regex.c line_replace)
+    InfiniteSubstitutionLoop,
+
+    #[error("?")]  // GNU ed: Unexpected end-of-file (io.c get_stdin_line)
+    UnexpectedEof,
+
+    #[error("?")]  // rust-ed: command would modify the buffer in read-only mode (-R)
+    ReadOnly,
+
+    #[error("?")]  // rust-ed: --safe-write found the file changed since it was read
+    FileChangedOnDisk,
+
+    #[error("?")]  // rust-ed: shell command exceeded RUST_ED_SHELL_TIMEOUT
+    ShellTimeout,
+
     #[error("")]  // File not found - NOT a fatal error in GNU ed
     FileNotFound,
 
@@ -60,15 +138,84 @@ impl EdError {
         match self {
             EdError::InvalidCommand => 1,
             EdError::InvalidAddress => 1,
+            EdError::UnexpectedAddress => 1,
             EdError::InvalidFilename => 1,
             EdError::IoError(_) => 1,
             EdError::NoMatch => 1,
             EdError::NothingToUndo => 1,
             EdError::NothingToPut => 1,
-            EdError::PatternNotFound => 1,
+            EdError::NoPreviousPattern => 1,
             EdError::WarningUnsavedChanges => 1,  // Exit code 1 to indicate error
+            EdError::TooManyLines => 1,
+            EdError::ShellTimeout => 1,
+            EdError::ReadOnly => 1,
+            EdError::UnexpectedEof => 1,
+            EdError::CannotOpenOutput => 1,
+            EdError::CannotWriteFile => 1,
+            EdError::CannotCloseOutput => 1,
+            EdError::InvalidPattern(_) => 1,
+            EdError::LineTooLong => 1,
+            EdError::InvalidDestination => 1,
+            EdError::NumberOutOfRange => 1,
+            EdError::NoPreviousCommand => 1,
+            EdError::NoCurrentFilename => 1,
+            EdError::ShellRestricted => 1,
+            EdError::CannotCreateShell => 1,
+            EdError::DirectoryRestricted => 1,
+            EdError::CannotNestGlobal => 1,
+            EdError::InvalidMarkCharacter => 1,
+            EdError::InfiniteSubstitutionLoop => 1,
+            EdError::FilenameTooLong => 1,
+            EdError::CannotReadInput => 1,
+            EdError::FileChangedOnDisk => 1,
+            EdError::InvalidCommandSuffix => 1,
+            EdError::UnexpectedCommandSuffix => 1,
+            EdError::UnknownCommand => 1,
             EdError::FileNotFound => 0,  // Not a fatal error - GNU ed continues
             EdError::Quit => 0,
         }
     }
+
+    /// Stable name of the error for tools reading RUST_ED_JSON_ERRORS output
+    pub fn name(&self) -> &'static str {
+        match self {
+            EdError::InvalidCommand => "InvalidCommand",
+            EdError::InvalidAddress => "InvalidAddress",
+            EdError::UnexpectedAddress => "UnexpectedAddress",
+            EdError::InvalidFilename => "InvalidFilename",
+            EdError::IoError(_) => "IoError",
+            EdError::NoMatch => "NoMatch",
+            EdError::NothingToUndo => "NothingToUndo",
+            EdError::NothingToPut => "NothingToPut",
+            EdError::NoPreviousPattern => "NoPreviousPattern",
+            EdError::WarningUnsavedChanges => "WarningUnsavedChanges",
+            EdError::TooManyLines => "TooManyLines",
+            EdError::ShellTimeout => "ShellTimeout",
+            EdError::ReadOnly => "ReadOnly",
+            EdError::UnexpectedEof => "UnexpectedEof",
+            EdError::CannotOpenOutput => "CannotOpenOutput",
+            EdError::CannotWriteFile => "CannotWriteFile",
+            EdError::CannotCloseOutput => "CannotCloseOutput",
+            EdError::InvalidPattern(_) => "InvalidPattern",
+            EdError::LineTooLong => "LineTooLong",
+            EdError::InvalidDestination => "InvalidDestination",
+            EdError::NumberOutOfRange => "NumberOutOfRange",
+            EdError::NoPreviousCommand => "NoPreviousCommand",
+            EdError::NoCurrentFilename => "NoCurrentFilename",
+            EdError::ShellRestricted => "ShellRestricted",
+            EdError::CannotCreateShell => "CannotCreateShell",
+            EdError::DirectoryRestricted => "DirectoryRestricted",
+            EdError::CannotNestGlobal => "CannotNestGlobal",
+            EdError::InvalidMarkCharacter => "InvalidMarkCharacter",
+            EdError::InfiniteSubstitutionLoop => "InfiniteSubstitutionLoop",
+            EdError::FilenameTooLong => "FilenameTooLong",
+            EdError::CannotReadInput => "CannotReadInput",
+            EdError::FileChangedOnDisk => "FileChangedOnDisk",
+            EdError::InvalidCommandSuffix => "InvalidCommandSuffix",
+            EdError::UnexpectedCommandSuffix => "UnexpectedCommandSuffix",
+            EdError::UnknownCommand => "UnknownCommand",
+            EdError::FileNotFound => "FileNotFound",
+            EdError::Quit => "Quit",
+        }
+    }
 }
\ No newline at end of file
@@ -24,29 +24,30 @@
 /// The original C code uses manual memory management for a dynamic array of line_node pointers.
 /// Our Rust version uses line indices with Vec<Option<usize>> for identical functionality.
 
-use std::sync::Mutex;
+use crate::session;
 
 // Safe Rust replacement for C's dynamic array of line_node pointers
-// In rust-ed we use line indices instead of pointers since our buffer is Vec-based
-static ACTIVE_LIST: Mutex<Vec<Option<usize>>> = Mutex::new(Vec::new());
-static ACTIVE_IDX: Mutex<usize> = Mutex::new(0);    // current iteration index
-static ACTIVE_IDXM: Mutex<usize> = Mutex::new(0);   // modulo index for removal
+// In rust-ed we use line indices instead of pointers since our buffer is Vec-based.
+// The list and its indices (active_idx, active_idxm) live in session::Session.
+//
+// A line node keeps its identity while lines are added or deleted around it;
+// an index does not. EdBuffer therefore reports every insertion, deletion
+// and move here, and the lines still to be visited are renumbered. Most
+// commands of a global change lines at or before the current one, which
+// moves all pending lines alike, so that case only adjusts active_offset;
+// a stored entry's address is the entry plus active_offset.
 
 /// clear_active_list - matches global.c:36 (now memory safe)
 pub fn clear_active_list() {
     // Safe Rust implementation - no manual memory management needed
     crate::signal::disable_interrupts();
     
-    // Clear all data safely using Mutex guards
-    if let Ok(mut list) = ACTIVE_LIST.lock() {
-        list.clear();
-    }
-    if let Ok(mut idx) = ACTIVE_IDX.lock() {
-        *idx = 0;
-    }
-    if let Ok(mut idxm) = ACTIVE_IDXM.lock() {
-        *idxm = 0;
-    }
+    session::with(|s| {
+        s.active_list.clear();
+        s.active_idx = 0;
+        s.active_idxm = 0;
+        s.active_offset = 0;
+    });
     
     crate::signal::enable_interrupts();
 }
@@ -54,20 +55,19 @@ pub fn clear_active_list() {
 /// next_active_node - matches global.c:47 (now memory safe)
 pub fn next_active_line() -> Option<usize> {
     // Safe implementation using line indices instead of raw pointers
-    let list = ACTIVE_LIST.lock().ok()?;
-    let mut idx = ACTIVE_IDX.lock().ok()?;
-    
-    // Find next active line, skipping None entries
-    while *idx < list.len() {
-        let current_idx = *idx;
-        *idx += 1;
-        
-        if let Some(line_addr) = list[current_idx] {
-            return Some(line_addr);
+    session::with(|s| {
+        // Find next active line, skipping None entries
+        while s.active_idx < s.active_list.len() {
+            let current_idx = s.active_idx;
+            s.active_idx += 1;
+
+            if let Some(entry) = s.active_list[current_idx] {
+                return Some((entry + s.active_offset) as usize);
+            }
         }
-    }
-    
-    None
+
+        None
+    })
 }
 
 /// set_active_node - matches global.c:56 (now memory safe) 
@@ -75,45 +75,89 @@ pub fn set_active_line(line_addr: usize) -> bool {
     // Safe implementation - Vec automatically manages memory
     crate::signal::disable_interrupts();
     
-    let result = if let Ok(mut list) = ACTIVE_LIST.lock() {
+    let result = session::with(|s| {
         // Check for reasonable limits to prevent DoS
-        if list.len() >= 10_000_000 {
+        if s.active_list.len() >= 10_000_000 {
             // TODO: set_error_msg("Too many matching lines");
             false
         } else {
-            list.push(Some(line_addr));
+            s.active_list.push(Some(line_addr as isize - s.active_offset));
             true
         }
-    } else {
-        false
-    };
+    });
     
     crate::signal::enable_interrupts();
     result
 }
 
+/// pending_range - lowest and highest address still to be visited (not in C original)
+fn pending_range(s: &session::Session) -> Option<(usize, usize)> {
+    let pending = &s.active_list[s.active_idx.min(s.active_list.len())..];
+    let first = pending.iter().flatten().next()?;
+    let last = pending.iter().rev().flatten().next()?;
+    Some(((first + s.active_offset) as usize, (last + s.active_offset) as usize))
+}
+
+/// renumber_pending - apply f to the address of every line still to be
+/// visited; f returns None for a line that is gone (not in C original)
+fn renumber_pending(s: &mut session::Session, f: impl Fn(usize) -> Option<usize>) {
+    let offset = s.active_offset;
+    let start = s.active_idx.min(s.active_list.len());
+    for entry in s.active_list[start..].iter_mut() {
+        if let Some(stored) = *entry {
+            *entry = f((stored + offset) as usize).map(|addr| addr as isize - offset);
+        }
+    }
+}
+
+/// shift_active_lines - lines were added or deleted just before from, so
+/// active lines at or after it move by delta (not in C original)
+pub fn shift_active_lines(from: usize, delta: isize) {
+    session::with(|s| match pending_range(s) {
+        Some((first, _)) if first >= from => s.active_offset += delta,
+        Some((_, last)) if last >= from => {
+            renumber_pending(s, |addr| Some(if addr >= from { (addr as isize + delta) as usize } else { addr }));
+        }
+        _ => {}
+    });
+}
+
 /// unset_active_lines - matches global.c:82 (now memory safe)
-pub fn unset_active_lines(start_addr: usize, end_addr: usize) {
-    // Safe implementation using line address ranges instead of raw pointers
+/// Lines from..=to are being deleted: they drop out of the active list and
+/// the lines after them move up
+pub fn unset_active_lines(from: usize, to: usize) {
     crate::signal::disable_interrupts();
-    
-    if let (Ok(mut list), Ok(mut idxm)) = (ACTIVE_LIST.lock(), ACTIVE_IDXM.lock()) {
-        // Remove all lines in the range [start_addr, end_addr)
-        for line_addr in start_addr..end_addr {
-            // Find and remove matching line addresses - matches global.c:86-92 logic
-            for _ in 0..list.len() {
-                if *idxm >= list.len() {
-                    *idxm = 0;
-                }
-                
-                if list[*idxm] == Some(line_addr) {
-                    list[*idxm] = None;  // Set to None instead of null pointer
-                    break;
-                }
-                *idxm += 1;
+
+    session::with(|s| {
+        if let Some((first, last)) = pending_range(s) {
+            if first <= to && last >= from {
+                renumber_pending(s, |addr| if (from..=to).contains(&addr) { None } else { Some(addr) });
             }
         }
-    }
-    
+    });
+    shift_active_lines(to + 1, -((to + 1 - from) as isize));
+
     crate::signal::enable_interrupts();
-}
\ No newline at end of file
+}
+
+/// move_active_lines - lines first..=second are being moved after addr,
+/// addressed as before the move (not in C original)
+pub fn move_active_lines(first: usize, second: usize, addr: usize) {
+    let n = second - first + 1;
+    session::with(|s| {
+        match pending_range(s) {
+            Some((low, _)) if low > second.max(addr) => return,
+            None => return,
+            _ => {}
+        }
+        renumber_pending(s, |line| Some(if (first..=second).contains(&line) {
+            if addr < first { line - first + addr + 1 } else { line - first + addr + 1 - n }
+        } else if addr < first && line > addr && line < first {
+            line + n
+        } else if addr > second && line > second && line <= addr {
+            line - n
+        } else {
+            line
+        }));
+    });
+}
@@ -21,51 +21,45 @@
 /// C source: io.c (365 lines, 11,091 bytes) - IMMUTABLE REFERENCE
 
 use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, Write, BufWriter};
-use std::process::{Command, Stdio};
-use std::sync::atomic::{AtomicI32, Ordering};
-use std::sync::Mutex;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write, BufWriter};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
 use crate::buffer::EdBuffer;
 use crate::error::EdError;
 // TODO: Import main module functions once module structure is finalized
 // use crate::main::{scripted, show_strerror, show_warning};
 
-// Static state converted to safe Rust - matches io.c functionality
-static LINENUM_: AtomicI32 = AtomicI32::new(0);              // script line number
-static UNTERMINATED_LINE: Mutex<Option<usize>> = Mutex::new(None);  // last line has no '\n'
+// io.c statics (linenum_, unterminated_line) live in session::Session
 
 /// linenum - matches io.c:30 (now memory safe)
 pub fn linenum() -> i32 {
-    LINENUM_.load(Ordering::Relaxed)
+    crate::session::with(|s| s.linenum)
 }
 
 /// reset_unterminated_line - matches io.c:32 (now memory safe)
 pub fn reset_unterminated_line() {
-    if let Ok(mut guard) = UNTERMINATED_LINE.lock() {
-        *guard = None;
-    }
+    crate::session::with(|s| s.unterminated_line = None);
 }
 
 /// unmark_unterminated_line - matches io.c:34 (now memory safe)
 pub fn unmark_unterminated_line(lp: usize) {
-    if let Ok(mut guard) = UNTERMINATED_LINE.lock() {
-        if let Some(line_addr) = *guard {
-            if line_addr == lp {
-                *guard = None;
-            }
+    crate::session::with(|s| {
+        if s.unterminated_line == Some(lp) {
+            s.unterminated_line = None;
         }
-    }
+    });
+}
+
+/// mark_unterminated_line - record that line lp has no '\n' (not in C original;
+/// join_lines uses it to carry the mark over to the joined line)
+pub fn mark_unterminated_line(lp: usize) {
+    crate::session::with(|s| s.unterminated_line = Some(lp));
 }
 
 /// unterminated_last_line - matches io.c:37 (now memory safe)
-fn unterminated_last_line(buffer: &EdBuffer) -> bool {
-    UNTERMINATED_LINE.lock().map_or(false, |guard| {
-        if let Some(line_addr) = *guard {
-            line_addr == buffer.last_addr()
-        } else {
-            false
-        }
-    })
+pub fn unterminated_last_line(buffer: &EdBuffer) -> bool {
+    let last = buffer.last_addr();
+    crate::session::with(|s| s.unterminated_line == Some(last))
 }
 
 /// escchar - matches io.c:42
@@ -83,47 +77,67 @@ pub fn escchar(ch: u8) -> char {
 }
 
 /// print_line - matches io.c:51
-fn print_line(p: &str, len: usize, pflags: i32, current_addr: usize) {
+pub fn print_line(p: &str, len: usize, pflags: i32, current_addr: usize) {
+    let mut out: Vec<u8> = Vec::with_capacity(len + 16);
     let mut col = 0;
 
     // Print line number if requested (pf_n flag) - exactly like GNU ed io.c:55
     if (pflags & 0x1) != 0 { // pf_n = 1
-        print!("{}\t", current_addr);  // GNU ed: printf( "%d\t", current_addr() );
+        out.extend_from_slice(format!("{}\t", current_addr).as_bytes());
         col = 8;
     }
-    
-    for ch in p.chars() {
-        if (pflags & 0x2) == 0 { // not pf_l
-            print!("{}", ch);
-        } else {
-            // List mode - escape special characters
-            match ch {
-                '\t' => print!("\\t"),
-                '\n' => print!("\\n"),
-                '\r' => print!("\\r"),
-                '\\' => print!("\\\\"),
-                c if c.is_control() => print!("\\{:03o}", c as u8),
-                c => print!("{}", c),
-            }
+
+    if (pflags & 0x2) == 0 { // not pf_l
+        out.extend_from_slice(&p.as_bytes()[..len.min(p.len())]);
+    } else {
+        // List mode works on bytes, as GNU ed does (io.c:59-79)
+        let columns = crate::signal::window_columns();
+        for &ch in &p.as_bytes()[..len.min(p.len())] {
             col += 1;
+            if col > columns {
+                col = 1;
+                out.extend_from_slice(b"\\\n");
+            }
+            if (32..=126).contains(&ch) {
+                if ch == b'$' || ch == b'\\' {
+                    col += 1;
+                    out.push(b'\\');
+                }
+                out.push(ch);
+            } else {
+                col += 1;
+                out.push(b'\\');
+                let e = escchar(ch);
+                if e != '\0' {
+                    out.push(e as u8);
+                } else {
+                    col += 2;
+                    out.push(((ch >> 6) & 7) + b'0');
+                    out.push(((ch >> 3) & 7) + b'0');
+                    out.push((ch & 7) + b'0');
+                }
+            }
         }
     }
-    
+
     // Add $ for list mode
-    if (pflags & 0x2) != 0 { // pf_l
-        println!("$");
-    } else {
-        println!();
+    if !crate::traditional() && (pflags & 0x2) != 0 { // pf_l
+        out.push(b'$');
     }
+    out.push(b'\n');
+    let mut stdout = std::io::stdout().lock();
+    let _ = stdout.write_all(&out);
 }
 
 /// print_lines - matches io.c:87
-pub fn print_lines(buffer: &EdBuffer, from: usize, to: usize, pflags: i32) -> Result<bool, EdError> {
-    if from > buffer.last_addr() || to > buffer.last_addr() || from > to {
+pub fn print_lines(buffer: &mut EdBuffer, from: usize, to: usize, pflags: i32) -> Result<bool, EdError> {
+    if from == 0 || from > buffer.last_addr() || to > buffer.last_addr() || from > to {
         return Err(EdError::InvalidAddress);
     }
     
     for line_num in from..=to {
+        // Each printed line becomes the current line (GNU ed io.c:96)
+        buffer.set_current_addr(line_num);
         if let Some(line) = buffer.get_sbuf_line(line_num) {
             print_line(line, line.len(), pflags, line_num);
         }
@@ -149,9 +163,23 @@ fn trailing_escape(s: &str, len: usize) -> bool {
 }
 
 /// get_extended_line - matches io.c:119
-pub fn get_extended_line(ibufpp: &str, buffer: &mut EdBuffer) -> Result<(String, usize), EdError> {
-    // TODO: Implement extended line reading (for multi-line commands)
-    Ok((ibufpp.to_string(), ibufpp.len()))
+/// While the line ends in an odd number of backslashes, the backslash is
+/// dropped and the next stdin line appended; the newline it escaped is kept
+/// unless strip_escaped_newlines.
+pub fn get_extended_line(ibufpp: &str, strip_escaped_newlines: bool) -> Result<String, EdError> {
+    let mut buf = ibufpp.to_string();
+    while trailing_escape(&buf, buf.chars().count()) {
+        buf.pop();
+        if !strip_escaped_newlines {
+            buf.push('\n');
+        }
+        let (line, len) = get_stdin_line()?;
+        if len == 0 {
+            return Err(EdError::UnexpectedEof);
+        }
+        buf.push_str(&line);
+    }
+    Ok(buf)
 }
 
 /// get_stdin_line - matches io.c:158
@@ -160,7 +188,7 @@ pub fn get_stdin_line() -> Result<(String, usize), EdError> {
     match std::io::stdin().read_line(&mut input) {
         Ok(bytes_read) => {
             let line = input.trim_end_matches('\n');
-            LINENUM_.fetch_add(1, Ordering::Relaxed);
+            crate::session::with(|s| s.linenum += 1);
             Ok((line.to_string(), bytes_read))
         },
         Err(_) => Err(EdError::InvalidCommand),
@@ -168,25 +196,27 @@ pub fn get_stdin_line() -> Result<(String, usize), EdError> {
 }
 
 /// read_stream_line - matches io.c:199
-fn read_stream_line(filename: &str, fp: &mut BufReader<File>, buffer: &mut EdBuffer) -> Result<String, EdError> {
+/// Also returns whether the line had no '\n' (io.c newline_added)
+fn read_stream_line<R: BufRead>(filename: &str, fp: &mut R, buffer: &mut EdBuffer) -> Result<(String, bool), EdError> {
     let mut line = String::new();
-    match fp.read_line(&mut line) {
+    match crate::signal::Interruptible(&mut *fp).read_line(&mut line) {
         Ok(0) => Err(EdError::InvalidCommand), // EOF
         Ok(_) => {
+            // GNU ed io.c:212 - a NUL byte makes the buffer binary
+            if line.contains('\0') {
+                buffer.set_binary();
+            }
             // Remove trailing newline if present
-            if line.ends_with('\n') {
+            let newline_added = !line.ends_with('\n');
+            if !newline_added {
                 line.pop();
-                // GNU ed io.c:213-214: remove CR only from CR/LF pairs
-                if line.ends_with('\r') {
+                // GNU ed io.c:213-214: with --strip-trailing-cr, remove CR
+                // only from CR/LF pairs
+                if crate::strip_cr() && line.ends_with('\r') {
                     line.pop();
                 }
-            } else {
-                // Mark as unterminated line
-                if let Ok(mut guard) = UNTERMINATED_LINE.lock() {
-                    *guard = Some(buffer.last_addr() + 1);
-                }
             }
-            Ok(line)
+            Ok((line, newline_added))
         },
         Err(_) => {
             // TODO: show_strerror(Some(filename), 1);
@@ -196,14 +226,32 @@ fn read_stream_line(filename: &str, fp: &mut BufReader<File>, buffer: &mut EdBuf
 }
 
 /// read_stream - matches io.c:240  
-fn read_stream(filename: &str, fp: &mut BufReader<File>, addr: usize, buffer: &mut EdBuffer) -> Result<i64, EdError> {
+/// Returns the size in bytes and the number of lines inserted after addr
+fn read_stream<R: BufRead>(filename: &str, fp: &mut R, addr: usize, buffer: &mut EdBuffer) -> Result<(i64, usize), EdError> {
     let mut total_size = 0i64;
     let mut current_addr = addr;
-    
+    let appended = addr == buffer.last_addr();
+    let o_unterminated_last_line = unterminated_last_line(buffer);
+    let mut newline_added = false;
+
+    // GNU ed io.c:250 - an empty stream still leaves the current line at addr
+    buffer.set_current_addr(addr);
     loop {
+        // A hangup stops a long read; the main loop saves what was read
+        if crate::signal::hangup_pending() {
+            return Err(EdError::CannotReadInput);
+        }
         match read_stream_line(filename, fp, buffer) {
-            Ok(line) => {
-                total_size += line.len() as i64 + 1; // +1 for newline
+            Ok((line, unterminated)) => {
+                newline_added = unterminated;
+                // Stop before exceeding max_buffer_lines (GNU ed buffer.c:208 too_many_lines)
+                // Lines already read stay in the buffer with their undo atoms
+                if buffer.too_many_lines() {
+                    return Err(EdError::TooManyLines);
+                }
+
+                // +1 for the newline, unless a binary line had none (io.c:230)
+                total_size += line.len() as i64 + i64::from(!unterminated || !buffer.isbinary());
                 
                 // Add line to buffer at current position
                 buffer.insert_line(current_addr, line)?;
@@ -213,17 +261,23 @@ fn read_stream(filename: &str, fp: &mut BufReader<File>, addr: usize, buffer: &m
         }
     }
     
-    // Update buffer state
-    if buffer.len() > 0 && !buffer.isbinary() {
-        // Safe update of unterminated line state (converted from unsafe)
-        if let Ok(mut guard) = UNTERMINATED_LINE.lock() {
-            if guard.is_some() {
-                *guard = Some(buffer.last_addr());
-            }
+    // GNU ed io.c:271-274
+    if !crate::scripted() {
+        if addr > 0 && appended && total_size > 0 && o_unterminated_last_line {
+            outln!("Newline inserted"); // before stream
+        } else if newline_added && (!appended || !buffer.isbinary()) {
+            outln!("Newline appended"); // after stream
         }
     }
+
+    // GNU ed io.c:279-280 - only a binary stream read in at the end can
+    // leave the last line without its '\n'
+    if appended && buffer.isbinary() && (newline_added || total_size == 0) {
+        let lp = buffer.last_addr();
+        crate::session::with(|s| s.unterminated_line = Some(lp));
+    }
     
-    Ok(total_size)
+    Ok((total_size, current_addr - addr))
 }
 
 /// read_file - matches io.c:288 (MAIN READ FUNCTION)
@@ -233,9 +287,12 @@ pub fn read_file(filename: &str, addr: usize, buffer: &mut EdBuffer) -> Result<i
         return read_shell_command(&filename[1..], addr, buffer);
     }
     
-    // Try to open file
-    let file = match File::open(filename) {
+    // Try to open file, read-only: GNU ed tries "r+" first, which leaves a
+    // FIFO with a writer (ed itself) so the read never sees EOF
+    let file = match open_input(filename) {
         Ok(f) => f,
+        // The main loop reports the interrupt
+        Err(e) if e.kind() == std::io::ErrorKind::Interrupted => return Err(EdError::CannotReadInput),
         Err(e) => {
             // Print error to stderr (GNU ed io.c show_strerror behavior)
             // Format to match GNU ed output (just "filename: error_description")
@@ -250,60 +307,204 @@ pub fn read_file(filename: &str, addr: usize, buffer: &mut EdBuffer) -> Result<i
         }
     };
     
+    reject_directory(filename, &file)?;
     let mut reader = BufReader::new(file);
     
     // Read file into buffer
-    let size = read_stream(filename, &mut reader, addr, buffer)?;
+    let was_binary = buffer.isbinary();
+    let (size, lines) = read_stream(filename, &mut reader, addr, buffer)?;
     
-    // Print file size if not in script mode
-    // TODO: Check scripted mode - if !scripted()
-    println!("{}", size);
+    // Print file size if not in script mode (GNU ed io.c:307)
+    if !crate::scripted() {
+        outln!("{}", size);
+    }
+    note_binary_file(filename, was_binary, buffer);
     
-    // Return line count
-    Ok((buffer.current_addr() - addr) as i32)
+    // Return line count, as counted by read_stream rather than taken from
+    // the current address (GNU ed io.c:308 returns current_addr() - addr)
+    Ok(lines as i32)
+}
+
+/// note_binary_file - with H on, tell an interactive user that a read made
+/// the buffer binary (not in C original); only on a terminal, like the
+/// verbose status line, so piped output stays identical to GNU ed
+pub fn note_binary_file(filename: &str, was_binary: bool, buffer: &EdBuffer) {
+    if let Some(note) = binary_file_note(was_binary, buffer, std::io::stderr().is_terminal()) {
+        crate::show_warning(Some(filename), note);
+    }
+}
+
+/// binary_file_note - the warning note_binary_file gives after the file
+/// name, if any, with the terminal check passed in (not in C original)
+fn binary_file_note(was_binary: bool, buffer: &EdBuffer, on_terminal: bool) -> Option<&'static str> {
+    let note = !was_binary && buffer.isbinary() && crate::main_loop::verbose() && on_terminal;
+    note.then_some("binary file")
+}
+
+/// open_input - open a file for reading (not in C original)
+/// File::open retries an open cut short by a signal, which would leave an
+/// interrupt unable to get ed out of waiting for a FIFO writer
+fn open_input(filename: &str) -> std::io::Result<File> {
+    use rustix::fs::{Mode, OFlags};
+    loop {
+        match rustix::fs::open(filename, OFlags::RDONLY | OFlags::CLOEXEC, Mode::empty()) {
+            Ok(fd) => return Ok(File::from(fd)),
+            Err(rustix::io::Errno::INTR)
+                if !crate::signal::interrupt_pending() && !crate::signal::hangup_pending() => {}
+            Err(errno) => return Err(errno.into()),
+        }
+    }
+}
+
+/// reject_directory - a directory opens, but reading it fails with EISDIR
+/// (GNU ed io.c:220-223); report that before anything is read
+pub fn reject_directory(filename: &str, file: &File) -> Result<(), EdError> {
+    if file.metadata().is_ok_and(|meta| meta.is_dir()) {
+        crate::show_strerror(Some(filename), nix::errno::Errno::EISDIR as i32);
+        return Err(EdError::CannotReadInput);
+    }
+    Ok(())
+}
+
+/// shell_timeout - optional limit on shell command run time (not in C original)
+/// Read from RUST_ED_SHELL_TIMEOUT in seconds; unset, zero or invalid means wait forever
+pub fn shell_timeout() -> Option<Duration> {
+    std::env::var("RUST_ED_SHELL_TIMEOUT").ok()
+        .and_then(|s| s.trim().parse::<u64>().ok())
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// shell_command - a /bin/sh -c command (not in C original)
+/// Under RUST_ED_SHELL_TIMEOUT it runs in its own process group, so the
+/// timeout can kill the whole pipeline; it then gets no terminal signals.
+pub fn shell_command(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut cmd = Command::new("/bin/sh");
+    cmd.arg("-c").arg(command);
+    if shell_timeout().is_some() {
+        cmd.process_group(0);
+    }
+    cmd
+}
+
+/// wait_child_output - wait for a shell child, killing it if the timeout expires
+/// Feeds input to its stdin and collects stdout (when piped) on separate
+/// threads, so a child that fills one pipe while ed fills the other can't
+/// block, and the timeout covers the whole run. Also returns how feeding
+/// stdin went; the pipe is closed once input is written. With a timeout the
+/// child is polled; on expiry its process group is killed, or the child
+/// alone if it has none of its own.
+pub fn wait_child_output(child: &mut Child, input: Option<Vec<u8>>, timeout: Option<Duration>) -> Result<(ExitStatus, Vec<u8>, std::io::Result<()>), EdError> {
+    let writer = input.and_then(|input| child.stdin.take().map(|mut stdin| {
+        std::thread::spawn(move || stdin.write_all(&input))
+    }));
+    let reader = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = stdout.read_to_end(&mut output);
+            output
+        })
+    });
+
+    let status = match timeout {
+        None => wait_child(child)?,
+        Some(limit) => {
+            let start = Instant::now();
+            loop {
+                if let Some(status) = child.try_wait().map_err(|_| EdError::CannotCreateShell)? {
+                    break status;
+                }
+                if crate::signal::hangup_pending() {
+                    return Err(EdError::InvalidCommand);
+                }
+                if start.elapsed() >= limit {
+                    let group = rustix::process::Pid::from_child(child);
+                    if rustix::process::kill_process_group(group, rustix::process::Signal::KILL).is_err() {
+                        let _ = child.kill();
+                    }
+                    let _ = child.wait();
+                    return Err(EdError::ShellTimeout);
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+    };
+
+    let output = reader.and_then(|handle| handle.join().ok()).unwrap_or_default();
+    let written = writer.map_or(Ok(()), |handle| handle.join().unwrap_or(Ok(())));
+    Ok((status, output, written))
+}
+
+/// wait_child - block until a shell child exits (system() in GNU ed)
+/// Unlike Child::wait, a hangup ends the wait at once, so the main loop can
+/// save the buffer (GNU ed's handler runs mid-system()).
+fn wait_child(child: &Child) -> Result<ExitStatus, EdError> {
+    use std::os::unix::process::ExitStatusExt;
+    let pid = rustix::process::Pid::from_child(child);
+    loop {
+        match rustix::process::waitpid(Some(pid), rustix::process::WaitOptions::empty()) {
+            Ok(Some((_, status))) => return Ok(ExitStatus::from_raw(status.as_raw())),
+            Err(rustix::io::Errno::INTR) if !crate::signal::hangup_pending() => {}
+            Err(rustix::io::Errno::INTR) => return Err(EdError::InvalidCommand),
+            _ => return Err(EdError::CannotCreateShell),
+        }
+    }
 }
 
 /// Helper function for shell command input
 fn read_shell_command(command: &str, addr: usize, buffer: &mut EdBuffer) -> Result<i32, EdError> {
-    let output = Command::new("/bin/sh")
-        .arg("-c")
-        .arg(command)
-        .output()
+    let mut child = shell_command(command)
+        .stdout(Stdio::piped())
+        .spawn()
         .map_err(|_| EdError::InvalidCommand)?;
-    
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut current_addr = addr;
-    
-    for line in stdout.lines() {
-        buffer.insert_line(current_addr, line.to_string())?;
-        current_addr += 1;
+    let (_status, output, _) = wait_child_output(&mut child, None, shell_timeout())?;
+
+    // The output goes through the same reader as a file (GNU ed io.c:301
+    // reads the popen stream with read_stream)
+    let (size, lines) = read_stream(command, &mut output.as_slice(), addr, buffer)?;
+    if !crate::scripted() {
+        outln!("{}", size);
     }
-    
-    // TODO: Check scripted mode - if !scripted()
-    println!("{}", stdout.len());
-    
-    Ok((current_addr - addr) as i32)
+
+    Ok(lines as i32)
+}
+
+/// write_error - a failed fputc in write_stream (GNU ed io.c:332-334)
+fn write_error(filename: &str, e: std::io::Error) -> EdError {
+    crate::show_strerror(Some(filename), e.raw_os_error().unwrap_or(nix::errno::Errno::EIO as i32));
+    EdError::CannotWriteFile
+}
+
+/// close_error - a failed fclose after write_stream (GNU ed io.c:360-362)
+fn close_error(filename: &str, e: std::io::Error) -> EdError {
+    crate::show_strerror(Some(filename), e.raw_os_error().unwrap_or(nix::errno::Errno::EIO as i32));
+    EdError::CannotCloseOutput
 }
 
 /// write_stream - matches io.c:315
 fn write_stream(filename: &str, fp: &mut BufWriter<File>, from: usize, to: usize, buffer: &EdBuffer) -> Result<i64, EdError> {
     let mut total_size = 0i64;
+    let trim = crate::trim_trailing_ws();
     
     for line_num in from..=to {
         if let Some(line) = buffer.get_sbuf_line(line_num) {
+            // --trim-trailing-ws: blanks are dropped from the file, not the buffer
+            let line = if trim { line.trim_end_matches([' ', '\t']) } else { line };
             // Write line content
-            fp.write_all(line.as_bytes()).map_err(|_| EdError::InvalidCommand)?;
+            fp.write_all(line.as_bytes()).map_err(|e| write_error(filename, e))?;
             total_size += line.len() as i64;
             
             // Add newline unless it's the last line and binary and unterminated
             if line_num != buffer.last_addr() || !buffer.isbinary() || !unterminated_last_line(buffer) {
-                fp.write_all(b"\n").map_err(|_| EdError::InvalidCommand)?;
+                fp.write_all(b"\n").map_err(|e| write_error(filename, e))?;
                 total_size += 1;
             }
         }
     }
     
-    fp.flush().map_err(|_| EdError::InvalidCommand)?;
+    // GNU ed io.c:360-362 - buffered data that cannot be flushed fails the close
+    fp.flush().map_err(|e| close_error(filename, e))?;
     Ok(total_size)
 }
 
@@ -314,6 +515,17 @@ pub fn write_file(filename: &str, mode: &str, from: usize, to: usize, buffer: &E
         return write_shell_command(&filename[1..], from, to, buffer);
     }
     
+    // A full write replaces the file only once the new text is complete
+    if mode == "w" {
+        if let Some(result) = write_replacing(filename, |fp| write_stream(filename, fp, from, to, buffer)) {
+            let size = result?;
+            if !crate::scripted() {
+                outln!("{}", size);
+            }
+            return Ok(if from > 0 && from <= to { (to - from + 1) as i32 } else { 0 });
+        }
+    }
+
     // Open file with specified mode
     let file = if mode == "w" {
         OpenOptions::new().write(true).truncate(true).create(true).open(filename)
@@ -326,8 +538,9 @@ pub fn write_file(filename: &str, mode: &str, from: usize, to: usize, buffer: &E
     let file = match file {
         Ok(f) => f,
         Err(e) => {
-            // TODO: show_strerror(Some(filename), e.raw_os_error().unwrap_or(1));
-            return Err(EdError::InvalidCommand);
+            // GNU ed io.c:355-356
+            crate::show_strerror(Some(filename), e.raw_os_error().unwrap_or(nix::errno::Errno::EIO as i32));
+            return Err(EdError::CannotOpenOutput);
         }
     };
     
@@ -336,73 +549,111 @@ pub fn write_file(filename: &str, mode: &str, from: usize, to: usize, buffer: &E
     // Write lines to file
     let size = write_stream(filename, &mut writer, from, to, buffer)?;
     
-    // Print bytes written if not in script mode
-    // TODO: Check scripted mode - if !scripted()
-    println!("{}", size);
+    // Print bytes written if not in script mode (GNU ed io.c:361)
+    if !crate::scripted() {
+        outln!("{}", size);
+    }
     
     // Return line count
     Ok(if from > 0 && from <= to { (to - from + 1) as i32 } else { 0 })
 }
 
+/// write_replacing - write a file through a temporary file in the same
+/// directory, renamed over it when complete (not in C original: GNU ed
+/// truncates the file first, so a failed write loses the old contents)
+/// Returns None when the file cannot be replaced this way without changing
+/// it in other ways - not a regular file, hard linked, not writable by the
+/// user, or owned by someone else - and the caller writes it in place instead.
+fn write_replacing(filename: &str, write: impl FnOnce(&mut BufWriter<File>) -> Result<i64, EdError>) -> Option<Result<i64, EdError>> {
+    use std::os::unix::fs::MetadataExt;
+    use std::path::Path;
+
+    // Replace the file a symlink points to, not the symlink
+    let (target, old) = match std::fs::canonicalize(filename) {
+        Ok(path) => {
+            let metadata = std::fs::metadata(&path).ok()?;
+            if !metadata.is_file() || metadata.nlink() > 1 {
+                return None;
+            }
+            // Nor a file the user may not write: the in-place open then
+            // reports why, as GNU ed does
+            OpenOptions::new().write(true).open(&path).ok()?;
+            (path, Some(metadata))
+        }
+        // An existing path that does not resolve, like /dev/stdout on a
+        // pipe, is a special file: never create a file in its place
+        Err(_) if std::fs::symlink_metadata(filename).is_ok() => return None,
+        Err(_) => (Path::new(filename).to_path_buf(), None),
+    };
+    let name = target.file_name()?.to_string_lossy().into_owned();
+    let temp = target.with_file_name(format!(".{}.rust-ed-{}", name, std::process::id()));
+    let file = OpenOptions::new().write(true).create_new(true).open(&temp).ok()?;
+
+    if let Some(old) = &old {
+        let new = file.metadata().ok();
+        if !new.is_some_and(|new| new.uid() == old.uid() && new.gid() == old.gid())
+            || file.set_permissions(old.permissions()).is_err() {
+            let _ = std::fs::remove_file(&temp);
+            return None;
+        }
+    }
+
+    let mut writer = BufWriter::new(file);
+    let result = write(&mut writer).and_then(|size| {
+        let file = writer.into_inner().map_err(|e| close_error(filename, e.into_error()))?;
+        file.sync_all().map_err(|e| close_error(filename, e))?;
+        std::fs::rename(&temp, &target).map_err(|e| {
+            crate::show_strerror(Some(filename), e.raw_os_error().unwrap_or(nix::errno::Errno::EIO as i32));
+            EdError::CannotOpenOutput
+        })?;
+        Ok(size)
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    Some(result)
+}
+
 /// Helper function for shell command output
 fn write_shell_command(command: &str, from: usize, to: usize, buffer: &EdBuffer) -> Result<i32, EdError> {
-    let mut child = Command::new("/bin/sh")
-        .arg("-c")
-        .arg(command)
+    let mut child = shell_command(command)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()
-        .map_err(|_| EdError::InvalidCommand)?;
+        .map_err(|e| {
+            // GNU ed io.c:354-355 - popen failed
+            crate::show_strerror(Some(&format!("!{}", command)), e.raw_os_error().unwrap_or(nix::errno::Errno::EIO as i32));
+            EdError::CannotOpenOutput
+        })?;
 
-    let mut bytes_written = 0;
-    if let Some(stdin) = child.stdin.as_mut() {
-        for line_num in from..=to {
-            if let Some(line) = buffer.get_sbuf_line(line_num) {
-                stdin.write_all(line.as_bytes()).map_err(|_| EdError::InvalidCommand)?;
-                bytes_written += line.len();
-                stdin.write_all(b"\n").map_err(|_| EdError::InvalidCommand)?;
-                bytes_written += 1;
-            }
+    let mut input = Vec::new();
+    for line_num in from..=to {
+        if let Some(line) = buffer.get_sbuf_line(line_num) {
+            input.extend_from_slice(line.as_bytes());
+            input.push(b'\n');
         }
     }
+    let bytes_written = input.len();
 
-    let output = child.wait_with_output().map_err(|_| EdError::InvalidCommand)?;
+    // The lines go in while the output comes back, all bounded by RUST_ED_SHELL_TIMEOUT
+    let (_status, stdout, written) = wait_child_output(&mut child, Some(input), shell_timeout())?;
 
     // Print the output from the shell command (GNU ed behavior for !cat example)
-    print!("{}", String::from_utf8_lossy(&output.stdout));
+    out!("{}", String::from_utf8_lossy(&stdout));
+
+    // A command that stops reading early fails the write (GNU ed io.c:332-334)
+    written.map_err(|e| write_error(&format!("!{}", command), e))?;
 
-    // Print byte count (GNU ed io.c:361)
-    // TODO: Check scripted mode - if !scripted()
-    println!("{}", bytes_written);
+    // Print byte count if not in script mode (GNU ed io.c:361)
+    if !crate::scripted() {
+        outln!("{}", bytes_written);
+    }
 
     Ok(if from > 0 && from <= to { (to - from + 1) as i32 } else { 0 })
 }
 
 // Additional utility functions for I/O operations
 
-/// Check if character needs escaping in list mode
-pub fn needs_escape(ch: char) -> bool {
-    ch.is_control() || ch == '$' || ch == '\\'
-}
-
-/// Format line for list mode output
-pub fn format_list_line(line: &str) -> String {
-    let mut result = String::new();
-    for ch in line.chars() {
-        match ch {
-            '\t' => result.push_str("\\t"),
-            '\n' => result.push_str("\\n"),
-            '\r' => result.push_str("\\r"),
-            '\\' => result.push_str("\\\\"),
-            '$' => result.push_str("\\$"),
-            c if c.is_control() => result.push_str(&format!("\\{:03o}", c as u8)),
-            c => result.push(c),
-        }
-    }
-    result.push('$');
-    result
-}
-
 /// current_addr - moved from buffer.rs to match C source usage in io.c
 pub fn current_addr(buffer: &EdBuffer) -> usize {
     // TODO: Access buffer's current address - need to expose field or use method
@@ -437,8 +688,9 @@ pub fn write_file_simple(buffer: &mut EdBuffer, filename: Option<&str>) -> Resul
     match std::fs::write(fname, &content) {
         Ok(()) => {
             buffer.clear_modified_flag();
-            // TODO: Check scripted mode before printing
-            println!("{}", content.len());
+            if !crate::scripted() {
+                outln!("{}", content.len());
+            }
             Ok(())
         },
         Err(_) => Err(EdError::InvalidCommand),
@@ -466,4 +718,192 @@ pub fn file_writable(filename: &str) -> bool {
     } else {
         false
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::SafetyLimits;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn read_file_stops_at_max_buffer_lines() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"one\ntwo\nthree\nfour\nfive\n").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut buffer = EdBuffer::with_limits(SafetyLimits {
+            max_buffer_lines: 3,
+            ..SafetyLimits::default()
+        });
+
+        assert!(matches!(read_file(path, 0, &mut buffer), Err(EdError::TooManyLines)));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.last_addr(), 3);
+        assert_eq!(buffer.get_line(3), Some("three"));
+    }
+
+    #[test]
+    fn binary_file_note_only_for_a_new_binary_buffer_on_a_terminal() {
+        let mut buffer = EdBuffer::new();
+        buffer.set_binary();
+        crate::session::with(|s| s.verbose = true);
+        assert_eq!(binary_file_note(false, &buffer, true), Some("binary file"));
+        // Piped stderr, a buffer already binary, or H off: nothing
+        assert_eq!(binary_file_note(false, &buffer, false), None);
+        assert_eq!(binary_file_note(true, &buffer, true), None);
+        crate::session::with(|s| s.verbose = false);
+        assert_eq!(binary_file_note(false, &buffer, true), None);
+        assert_eq!(binary_file_note(false, &EdBuffer::new(), true), None);
+    }
+
+    #[test]
+    fn wait_child_output_kills_command_after_timeout() {
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg("sleep 5")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let start = Instant::now();
+        let result = wait_child_output(&mut child, None, Some(Duration::from_millis(100)));
+        assert!(matches!(result, Err(EdError::ShellTimeout)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn wait_child_output_kills_the_whole_pipeline_after_timeout() {
+        use std::os::unix::process::CommandExt;
+        let dir = tempfile::tempdir().unwrap();
+        let pidfile = dir.path().join("pid");
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg(format!("sleep 30 & echo $! > {}; wait", pidfile.display()))
+            .process_group(0)
+            .spawn()
+            .unwrap();
+
+        let result = wait_child_output(&mut child, None, Some(Duration::from_millis(300)));
+        assert!(matches!(result, Err(EdError::ShellTimeout)));
+        let pid = std::fs::read_to_string(&pidfile).unwrap().trim().parse::<i32>().unwrap();
+        let proc_dir = std::path::PathBuf::from(format!("/proc/{}", pid));
+        let start = Instant::now();
+        while proc_dir.exists() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!proc_dir.exists(), "sleep outlived its shell");
+    }
+
+    #[test]
+    fn wait_child_output_waits_without_a_timeout() {
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg("sleep 0.2; exit 3")
+            .spawn()
+            .unwrap();
+
+        let (status, _, _) = wait_child_output(&mut child, None, None).unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+
+    #[test]
+    fn wait_child_output_times_out_while_feeding_stdin() {
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg("sleep 5")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        // Far more than a pipe holds, so the write blocks until the child dies
+        let start = Instant::now();
+        let result = wait_child_output(&mut child, Some(vec![b'x'; 4 << 20]), Some(Duration::from_millis(100)));
+        assert!(matches!(result, Err(EdError::ShellTimeout)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn wait_child_output_passes_input_larger_than_a_pipe() {
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg("cat")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let input: Vec<u8> = (0..4 << 20).map(|i| b'a' + (i % 26) as u8).collect();
+        let (status, output, written) = wait_child_output(&mut child, Some(input.clone()), Some(Duration::from_secs(30))).unwrap();
+        assert!(status.success());
+        assert!(written.is_ok());
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn wait_child_output_collects_stdout() {
+        let mut child = Command::new("/bin/sh")
+            .arg("-c")
+            .arg("printf 'a\\nb\\n'")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let (status, output, _) = wait_child_output(&mut child, None, Some(Duration::from_secs(5))).unwrap();
+        assert!(status.success());
+        assert_eq!(output, b"a\nb\n");
+    }
+
+    #[test]
+    fn interrupted_write_keeps_the_old_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, "old\n").unwrap();
+
+        let result = write_replacing(path.to_str().unwrap(), |fp| {
+            fp.write_all(b"partial").unwrap();
+            Err(EdError::InvalidCommand)
+        });
+        assert!(matches!(result, Some(Err(EdError::InvalidCommand))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn read_only_file_is_not_replaced() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // root may write any file, so only an ordinary user sees the refusal
+        if unsafe { libc::geteuid() } == 0 {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, "old\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o444)).unwrap();
+
+        let result = write_replacing(path.to_str().unwrap(), |fp| {
+            fp.write_all(b"new\n").unwrap();
+            Ok(4)
+        });
+        assert!(result.is_none());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "old\n");
+    }
+
+    #[test]
+    fn completed_write_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("file");
+        std::fs::write(&path, "old\n").unwrap();
+
+        let result = write_replacing(path.to_str().unwrap(), |fp| {
+            fp.write_all(b"new\n").unwrap();
+            Ok(4)
+        });
+        assert!(matches!(result, Some(Ok(4))));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
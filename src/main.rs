@@ -16,39 +16,58 @@
 // You should have received a copy of the GNU General Public License
 // along with rust-ed.  If not, see <https://www.gnu.org/licenses/>.
 
-/// GNU ed main entry point - Rust translation
-/// This file matches main.c structure for human review
-/// C source: main.c (12,515 bytes) - IMMUTABLE REFERENCE
-///
-/// ═══════════════════════════════════════════════════════════════════════════
-/// 🚨 STRUCTURAL BOUNDARIES - DO NOT VIOLATE 🚨
-/// ═══════════════════════════════════════════════════════════════════════════
-///
-/// THIS FILE (main.rs) SHOULD CONTAIN (matching main.c):
-/// ✓ main() - Program entry point and argument parsing
-/// ✓ Global flag accessors (extended_regexp, restricted, scripted, etc.)
-/// ✓ Help and version display functions
-/// ✓ Error display utilities (show_error, show_warning, show_strerror)
-/// ✓ Utility functions (parse_addr, interactive, may_access_filename)
-/// ✓ Command dispatcher (execute_ed_command) that CALLS main_loop functions
-///
-/// THIS FILE MUST NOT CONTAIN:
-/// ✗ NO execute_*_command() implementations (those belong in main_loop.rs)
-/// ✗ NO command parsing logic (belongs in main_loop.rs)
-/// ✗ NO address extraction (belongs in main_loop.rs)
-/// ✗ NO buffer manipulation beyond dispatch (belongs in main_loop.rs)
-///
-/// COMMAND EXECUTION BELONGS IN main_loop.rs:
-/// - All execute_*_command() functions match main_loop.c:567-730 switch cases
-/// - If implementing a command, add it to main_loop.rs, NOT here
-/// - This file only dispatches to main_loop functions via execute_ed_command()
-///
-/// TARGET: ~12-18 functions (currently tracking C source exactly)
-/// ═══════════════════════════════════════════════════════════════════════════
+//! GNU ed main entry point - Rust translation
+//! This file matches main.c structure for human review
+//! C source: main.c (12,515 bytes) - IMMUTABLE REFERENCE
+//!
+//! ═══════════════════════════════════════════════════════════════════════════
+//! 🚨 STRUCTURAL BOUNDARIES - DO NOT VIOLATE 🚨
+//! ═══════════════════════════════════════════════════════════════════════════
+//!
+//! THIS FILE (main.rs) SHOULD CONTAIN (matching main.c):
+//! ✓ main() - Program entry point and argument parsing
+//! ✓ Global flag accessors (extended_regexp, restricted, scripted, etc.)
+//! ✓ Help and version display functions
+//! ✓ Error display utilities (show_error, show_warning, show_strerror)
+//! ✓ Utility functions (parse_addr, interactive, may_access_filename)
+//! ✓ Command dispatcher (execute_ed_command) that CALLS main_loop functions
+//!
+//! THIS FILE MUST NOT CONTAIN:
+//! ✗ NO execute_*_command() implementations (those belong in main_loop.rs)
+//! ✗ NO command parsing logic (belongs in main_loop.rs)
+//! ✗ NO address extraction (belongs in main_loop.rs)
+//! ✗ NO buffer manipulation beyond dispatch (belongs in main_loop.rs)
+//!
+//! COMMAND EXECUTION BELONGS IN main_loop.rs:
+//! - All execute_*_command() functions match main_loop.c:567-730 switch cases
+//! - If implementing a command, add it to main_loop.rs, NOT here
+//! - This file only dispatches to main_loop functions via execute_ed_command()
+//!
+//! TARGET: ~12-18 functions (currently tracking C source exactly)
+//! ═══════════════════════════════════════════════════════════════════════════
 
 use std::env;
 use std::process;
 
+/// out!/outln! - print!/println! for everything ed writes to stdout (not in C original)
+/// GNU ed ignores SIGPIPE (signal.c:152) and never checks stdio write errors,
+/// so output to a closed pipe is dropped and the session goes on; std's
+/// print! would panic instead.
+macro_rules! out {
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        let _ = write!(std::io::stdout(), $($arg)*);
+    }};
+}
+
+macro_rules! outln {
+    () => { out!("\n") };
+    ($($arg:tt)*) => {{
+        use std::io::Write as _;
+        let _ = writeln!(std::io::stdout(), $($arg)*);
+    }};
+}
+
 mod buffer;
 // mod commands; // REMOVED: Old modular approach superseded by main.rs implementations
 // mod address; // REMOVED: Functions integrated into main_loop.rs
@@ -60,142 +79,167 @@ mod global;
 mod carg_parser;
 mod signal;
 mod io;
+mod session;
 
 use error::EdError;
 use buffer::EdBuffer;
 use main_loop::main_loop;
 use ::regex::{Regex, RegexBuilder};
 
-// Global configuration flags - converted to safe atomic variables
-use std::sync::atomic::{AtomicBool, Ordering};
-
-static EXTENDED_REGEXP: AtomicBool = AtomicBool::new(false);
-static RESTRICTED: AtomicBool = AtomicBool::new(false);
-static SCRIPTED: AtomicBool = AtomicBool::new(false);
-static STRIP_CR: AtomicBool = AtomicBool::new(false);
-static TRADITIONAL: AtomicBool = AtomicBool::new(false);
-static QUIET: AtomicBool = AtomicBool::new(false);
-static SAFE_NAMES: AtomicBool = AtomicBool::new(true);
-static PROMPT_ON: AtomicBool = AtomicBool::new(false);
-
 static PROGRAM_NAME: &str = "ed";
 static PROGRAM_YEAR: &str = "2025";
 
+// Configuration flags - kept per editor in session::Session
+
 /// extended_regexp - matches main.c:62 (now memory safe)
 pub fn extended_regexp() -> bool { 
-    EXTENDED_REGEXP.load(Ordering::Relaxed)
+    session::with(|s| s.extended_regexp)
 }
 
 /// restricted - matches main.c:63 (now memory safe)
 pub fn restricted() -> bool { 
-    RESTRICTED.load(Ordering::Relaxed)
+    session::with(|s| s.restricted)
+}
+
+/// read_only - rust-ed extension: buffer mutations rejected (-R, --read-only)
+pub fn read_only() -> bool {
+    session::with(|s| s.read_only)
 }
 
 /// safe_names_enabled - accessor for SAFE_NAMES global (now memory safe)
 pub fn safe_names_enabled() -> bool {
-    SAFE_NAMES.load(Ordering::Relaxed)
+    session::with(|s| s.safe_names)
 }
 
 /// scripted - matches main.c:64 (now memory safe)
 pub fn scripted() -> bool { 
-    SCRIPTED.load(Ordering::Relaxed)
+    session::with(|s| s.scripted)
+}
+
+/// trim_trailing_ws - strip trailing blanks from lines as they are written
+/// (not in C original; off by default)
+pub fn trim_trailing_ws() -> bool {
+    session::with(|s| s.trim_trailing_ws)
+}
+
+/// safe_write - refuse the first 'w' over a file changed since it was read
+/// (not in C original; off by default)
+pub fn safe_write() -> bool {
+    session::with(|s| s.safe_write)
 }
 
 /// strip_cr - matches main.c:65 (now memory safe)
 pub fn strip_cr() -> bool { 
-    STRIP_CR.load(Ordering::Relaxed)
+    session::with(|s| s.strip_cr)
 }
 
 /// traditional - matches main.c:66 (now memory safe)
 pub fn traditional() -> bool {
-    TRADITIONAL.load(Ordering::Relaxed)
+    session::with(|s| s.traditional)
 }
 
 /// quiet - check if quiet mode is enabled
 pub fn quiet() -> bool {
-    QUIET.load(Ordering::Relaxed)
+    session::with(|s| s.quiet)
 }
 
 /// prompt_on - check if prompt is enabled
 pub fn prompt_on() -> bool {
-    PROMPT_ON.load(Ordering::Relaxed)
+    session::with(|s| s.prompt_on)
 }
 
 /// toggle_prompt - toggle prompt flag (GNU ed main_loop.c:668)
 pub fn toggle_prompt() {
-    let current = PROMPT_ON.load(Ordering::Relaxed);
-    PROMPT_ON.store(!current, Ordering::Relaxed);
+    session::with(|s| s.prompt_on = !s.prompt_on);
 }
 
 /// show_help - matches main.c:69
 fn show_help() {
-    println!("GNU ed is a line-oriented text editor. It is used to create, display,");
-    println!("modify and otherwise manipulate text files, both interactively and via");
-    println!("shell scripts. A restricted version of ed, red, can only edit files in");
-    println!("the current directory and cannot execute shell commands. Ed is the");
-    println!("'standard' text editor in the sense that it is the original editor for");
-    println!("Unix, and thus widely available. For most purposes, however, it is");
-    println!("superseded by full-screen editors.");
-    println!();
-    println!("Usage: {} [options] [[+line] file]", PROGRAM_NAME);
-    println!();
-    println!("The file name may be preceded by '+line', '+/RE', or '+?RE' to set the");
-    println!("current line to the line number specified or to the first or last line");
-    println!("matching the regular expression 'RE'.");
-    println!();
-    println!("The environment variable LINES can be used to set the initial window size.");
-    println!();
-    println!("Options:");
-    println!("  -h, --help                 display this help and exit");
-    println!("  -V, --version              output version information and exit");
-    println!("  -E, --extended-regexp      use extended regular expressions");
-    println!("  -G, --traditional          run in compatibility mode");
-    println!("  -l, --loose-exit-status    exit with 0 status even if a command fails");
-    println!("  -p, --prompt=STRING        use STRING as an interactive prompt");
-    println!("  -q, --quiet, --silent      suppress diagnostics written to stderr");
-    println!("  -r, --restricted           run in restricted mode");
-    println!("  -s, --script               suppress byte counts and '!' prompt");
-    println!("  -v, --verbose              be verbose; equivalent to the 'H' command");
-    println!("      --strip-trailing-cr    strip carriage returns at end of text lines");
-    println!("      --unsafe-names         allow control characters in file names");
-    println!();
-    println!("Start edit by reading in 'file' if given.");
-    println!("If 'file' begins with a '!', read output of shell command.");
-    println!();
-    println!("Exit status: 0 for a normal exit, 1 for environmental problems");
-    println!("(invalid command-line options, memory exhausted, command failed, etc),");
-    println!("2 for problems with the input file (file not found, buffer modified,");
-    println!("I/O errors), 3 for an internal consistency error (e.g., bug) which caused");
-    println!("ed to panic.");
-    println!();
-    println!("Report bugs to bug-ed@gnu.org");
-    println!("Ed home page: http://www.gnu.org/software/ed/ed.html");
-    println!("General help using GNU software: http://www.gnu.org/gethelp");
+    outln!("GNU ed is a line-oriented text editor. It is used to create, display,");
+    outln!("modify and otherwise manipulate text files, both interactively and via");
+    outln!("shell scripts. A restricted version of ed, red, can only edit files in");
+    outln!("the current directory and cannot execute shell commands. Ed is the");
+    outln!("'standard' text editor in the sense that it is the original editor for");
+    outln!("Unix, and thus widely available. For most purposes, however, it is");
+    outln!("superseded by full-screen editors.");
+    outln!();
+    outln!("Usage: {} [options] [[+line] file]", PROGRAM_NAME);
+    outln!();
+    outln!("The file name may be preceded by '+line', '+/RE', or '+?RE' to set the");
+    outln!("current line to the line number specified or to the first or last line");
+    outln!("matching the regular expression 'RE'.");
+    outln!();
+    outln!("The environment variable LINES can be used to set the initial window size.");
+    outln!();
+    outln!("Options:");
+    outln!("  -h, --help                 display this help and exit");
+    outln!("  -V, --version              output version information and exit");
+    outln!("  -E, --extended-regexp      use extended regular expressions");
+    outln!("  -G, --traditional          run in compatibility mode");
+    outln!("  -l, --loose-exit-status    exit with 0 status even if a command fails");
+    outln!("  -p, --prompt=STRING        use STRING as an interactive prompt");
+    outln!("  -q, --quiet, --silent      suppress diagnostics written to stderr");
+    outln!("  -r, --restricted           run in restricted mode");
+    outln!("  -R, --read-only            reject commands that modify the buffer");
+    outln!("  -s, --script               suppress byte counts and '!' prompt");
+    outln!("  -v, --verbose              be verbose; equivalent to the 'H' command");
+    outln!("      --strip-trailing-cr    strip carriage returns at end of text lines");
+    outln!("      --unsafe-names         allow control characters in file names");
+    outln!("      --trim-trailing-ws     strip trailing blanks from lines when writing");
+    outln!("      --safe-write           warn before 'w' overwrites a file changed on disk");
+    outln!();
+    outln!("Start edit by reading in 'file' if given.");
+    outln!("If 'file' begins with a '!', read output of shell command.");
+    outln!();
+    outln!("Exit status: 0 for a normal exit, 1 for environmental problems");
+    outln!("(invalid command-line options, memory exhausted, command failed, etc),");
+    outln!("2 for problems with the input file (file not found, buffer modified,");
+    outln!("I/O errors), 3 for an internal consistency error (e.g., bug) which caused");
+    outln!("ed to panic.");
+    outln!();
+    outln!("Report bugs to bug-ed@gnu.org");
+    outln!("Ed home page: http://www.gnu.org/software/ed/ed.html");
+    outln!("General help using GNU software: http://www.gnu.org/gethelp");
 }
 
 /// show_version - matches main.c:109
 fn show_version() {
-    println!("rust-ed {} (GNU ed 1.22.2 compatible)", "1.22.2-rust");
-    println!("Copyright (C) {} Brian Boynton, MD.", PROGRAM_YEAR);
-    println!("Based on GNU ed - Copyright (C) 1994 Andrew L. Moore, 2006-2025 Free Software Foundation, Inc.");
-    println!("License GPLv3+: GNU GPL version 3 or later <http://gnu.org/licenses/gpl.html>");
-    println!("This is free software: you are free to change and redistribute it.");
-    println!("There is NO WARRANTY, to the extent permitted by law.");
+    outln!("GNU ed 1.22.2 (rust-ed {})", env!("CARGO_PKG_VERSION"));
+    outln!("Copyright (C) {} Brian Boynton, MD.", PROGRAM_YEAR);
+    outln!("Based on GNU ed - Copyright (C) 1994 Andrew L. Moore, 2006-2025 Free Software Foundation, Inc.");
+    outln!("License GPLv3+: GNU GPL version 3 or later <http://gnu.org/licenses/gpl.html>");
+    outln!("This is free software: you are free to change and redistribute it.");
+    outln!("There is NO WARRANTY, to the extent permitted by law.");
 }
 
 /// print_escaped - matches main.c:120
 pub fn print_escaped(p: &str, to_stdout: bool) {
-    // TODO: Implement escaped character printing matching GNU ed exactly
+    let mut escaped = String::with_capacity(p.len());
+    for &ch in p.as_bytes() {
+        if ch == b'\\' {
+            escaped.push_str("\\\\");
+        } else if (32..=126).contains(&ch) {
+            escaped.push(ch as char);
+        } else {
+            // Non-printable bytes, including each byte of a multibyte
+            // character, are shown as a C escape or as three octal digits
+            escaped.push('\\');
+            match crate::io::escchar(ch) {
+                '\0' => escaped.push_str(&format!("{:03o}", ch)),
+                esc => escaped.push(esc),
+            }
+        }
+    }
     if to_stdout {
-        print!("{}", p);
+        out!("{}", escaped);
     } else {
-        eprint!("{}", p);
+        eprint!("{}", escaped);
     }
 }
 
 /// show_warning - matches main.c:137
 pub fn show_warning(filename: Option<&str>, msg: &str) {
-    if !QUIET.load(Ordering::Relaxed) {
+    if !session::with(|s| s.quiet) {
         if let Some(fname) = filename {
             if !fname.is_empty() {
                 print_escaped(fname, false);
@@ -208,15 +252,16 @@ pub fn show_warning(filename: Option<&str>, msg: &str) {
 
 /// show_strerror - matches main.c:148
 pub fn show_strerror(filename: Option<&str>, errcode: i32) {
-    if !QUIET.load(Ordering::Relaxed) {
+    if !session::with(|s| s.quiet) {
         if let Some(fname) = filename {
             if !fname.is_empty() {
                 print_escaped(fname, false);
                 eprint!(": ");
             }
         }
-        // TODO: Map errcode to actual system error message
-        eprintln!("Error code: {}", errcode);
+        // strerror(errcode); Rust appends " (os error N)", which GNU ed does not print
+        let msg = std::io::Error::from_raw_os_error(errcode).to_string();
+        eprintln!("{}", msg.split(" (os error").next().unwrap_or(&msg));
     }
 }
 
@@ -232,6 +277,20 @@ fn show_error(msg: &str, errcode: i32, help: bool) {
     }
 }
 
+/// internal_error - panic hook for rust-ed bugs (not in C original)
+/// Prints a short diagnostic and exits 3, the status show_help documents
+/// for internal consistency errors, instead of Rust's default 101
+fn internal_error(info: &std::panic::PanicHookInfo) {
+    let msg = info.payload().downcast_ref::<&str>().copied()
+        .or_else(|| info.payload().downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    let location = info.location()
+        .map(|loc| format!(" at {}:{}", loc.file(), loc.line()))
+        .unwrap_or_default();
+    eprintln!("{}: internal error: {}{}", PROGRAM_NAME, msg, location);
+    process::exit(3);
+}
+
 /// parse_addr - DEPRECATED: moved to carg_parser.rs for C structure alignment  
 fn parse_addr(arg: &str) -> i32 {
     // Address parsing functionality moved to carg_parser module
@@ -246,42 +305,130 @@ pub fn interactive() -> bool {
     !scripted()
 }
 
-/// may_access_filename - matches main.c:193
+// may_access_filename (main.c:193) lives in main_loop.rs
 
 /// main - matches main.c:218 (PROGRAM ENTRY POINT)
 fn main() {
+    std::panic::set_hook(Box::new(internal_error));
+    // Lets the test suite check the exit status of an internal error;
+    // release builds ignore it
+    #[cfg(debug_assertions)]
+    if env::var_os("RUST_ED_TEST_PANIC").is_some() {
+        panic!("RUST_ED_TEST_PANIC is set");
+    }
+
     let args: Vec<String> = env::args().collect();
     
     let mut initial_error = false;
-    let loose = false;
-    
-    // Argument parsing moved to carg_parser.rs module to match C structure
-    // Use carg_parser::ap_init, carg_parser::ap_code, etc. for GNU ed compatible parsing
-    // TODO: Integrate with carg_parser module functions
+    let mut loose = false;
+    const OPT_CR: i32 = 256;
+    const OPT_UN: i32 = 257;
+    const OPT_TW: i32 = 258;
+    const OPT_SW: i32 = 259;
+
+    // Option table - matches main.c:222-237 (plus rust-ed's read-only mode)
+    use carg_parser::ApHasArg::{ApNo, ApYes};
+    let option = |code: i32, name: &str, has_arg: carg_parser::ApHasArg| carg_parser::ApOption {
+        code,
+        name: Some(name.to_string()),
+        has_arg,
+    };
+    let options = [
+        option(b'E' as i32, "extended-regexp", ApNo),
+        option(b'G' as i32, "traditional", ApNo),
+        option(b'h' as i32, "help", ApNo),
+        option(b'l' as i32, "loose-exit-status", ApNo),
+        option(b'p' as i32, "prompt", ApYes),
+        option(b'q' as i32, "quiet", ApNo),
+        option(b'q' as i32, "silent", ApNo),
+        option(b'r' as i32, "restricted", ApNo),
+        option(b'R' as i32, "read-only", ApNo),
+        option(b's' as i32, "script", ApNo),
+        option(b'v' as i32, "verbose", ApNo),
+        option(b'V' as i32, "version", ApNo),
+        option(OPT_CR, "strip-trailing-cr", ApNo),
+        option(OPT_UN, "unsafe-names", ApNo),
+        option(OPT_TW, "trim-trailing-ws", ApNo),
+        option(OPT_SW, "safe-write", ApNo),
+    ];
+
+    let mut parser = carg_parser::ArgParser::new();
+    if !carg_parser::ap_init(&mut parser, &args, &options, false) {
+        show_error("Memory exhausted.", 0, false);
+        process::exit(1);
+    }
+    if let Some(msg) = carg_parser::ap_error(&parser) {
+        // bad option
+        show_error(msg, 0, true);
+        process::exit(1);
+    }
+
+    let mut argind = 0;
+    while argind < carg_parser::ap_arguments(&parser) {
+        let code = carg_parser::ap_code(&parser, argind);
+        if code == 0 {
+            break; // no more options
+        }
+        let arg = carg_parser::ap_argument(&parser, argind).unwrap_or("");
+        match code {
+            c if c == b'E' as i32 => session::with(|s| s.extended_regexp = true),
+            c if c == b'G' as i32 => session::with(|s| s.traditional = true),
+            c if c == b'h' as i32 => { show_help(); process::exit(0); },
+            c if c == b'l' as i32 => loose = true,
+            c if c == b'p' as i32 => if !main_loop::set_prompt(arg) { process::exit(1); },
+            c if c == b'q' as i32 => session::with(|s| s.quiet = true),
+            c if c == b'r' as i32 => session::with(|s| s.restricted = true),
+            c if c == b'R' as i32 => session::with(|s| s.read_only = true),
+            c if c == b's' as i32 => session::with(|s| s.scripted = true),
+            c if c == b'v' as i32 => main_loop::set_verbose(),
+            c if c == b'V' as i32 => { show_version(); process::exit(0); },
+            OPT_CR => session::with(|s| s.strip_cr = true),
+            OPT_UN => session::with(|s| s.safe_names = false),
+            OPT_TW => session::with(|s| s.trim_trailing_ws = true),
+            OPT_SW => session::with(|s| s.safe_write = true),
+            _ => {
+                show_error("internal error: uncaught option.", 0, false);
+                process::exit(3);
+            }
+        }
+        argind += 1;
+    }
+
     let mut filename: Option<String> = None;
-    
-    // Temporary simplified parsing until full carg_parser integration
-    for arg in args.iter().skip(1) {
-        if arg.starts_with('-') {
-            match arg.as_str() {
-                "-h" | "--help" => {
-                    show_help();
-                    process::exit(0);
-                },
-                "-V" | "--version" => {
-                    show_version();
-                    process::exit(0);
-                },
-                _ => {
-                    show_error(&format!("Unknown option: {}", arg), 0, true);
-                    process::exit(1);
+    while argind < carg_parser::ap_arguments(&parser) {
+        let arg = carg_parser::ap_argument(&parser, argind).unwrap_or("");
+        argind += 1;
+        // a hyphen operand '-' is equivalent to the option '-s'
+        if arg == "-" {
+            session::with(|s| s.scripted = true);
+            continue;
+        }
+        match main_loop::may_access_filename(arg) {
+            Ok(()) => filename = Some(arg.to_string()),
+            Err(err) => {
+                initial_error = true;
+                if !interactive() {
+                    process::exit(2);
                 }
+                // GNU ed main.c:316 show_warning( arg, error_msg() )
+                let msg = match err {
+                    EdError::ShellRestricted => "Shell access restricted",
+                    EdError::DirectoryRestricted => "Directory access restricted",
+                    _ => "Invalid filename",
+                };
+                main_loop::set_error_msg(msg);
+                show_warning(Some(arg), msg);
             }
-        } else if main_loop::may_access_filename(arg) {
-            filename = Some(arg.clone());
-            break;
         }
+        break; // extra arguments after file are ignored
     }
+    // GNU ed drops them silently; say so, so a mistyped command line is noticed
+    while argind < carg_parser::ap_arguments(&parser) {
+        let arg = carg_parser::ap_argument(&parser, argind).unwrap_or("");
+        show_warning(Some(arg), "Extra file name ignored");
+        argind += 1;
+    }
+    carg_parser::ap_free(&mut parser);
     
     // Initialize buffers (matches C init_buffers())
     let mut buffer = EdBuffer::new();
@@ -301,20 +448,27 @@ fn main() {
                 Ok(bytes_read) => {
                     // File exists (even if empty) - print byte count
                     if !scripted() {
-                        println!("{}", bytes_read);
+                        outln!("{}", bytes_read);
                     }
+                    crate::io::note_binary_file(&fname, false, &buffer);
                 },
                 Err(EdError::FileNotFound) => {
                     // File doesn't exist - already printed to stderr in load_file
-                    // GNU ed: don't print byte count, don't exit, continue with empty buffer
-                    // Do nothing - just continue to main_loop
+                    // GNU ed: don't print byte count, don't exit, continue with empty buffer;
+                    // 'h' explains the failed open (main.c:316 error_msg)
+                    main_loop::set_error_msg("Cannot open input file");
                 },
-                Err(_) => {
+                Err(err) => {
                     // Real I/O errors (not just missing file)
                     initial_error = true;
                     if !interactive() {
                         process::exit(2);
                     }
+                    // GNU ed main.c:316 show_warning( arg, error_msg() )
+                    if let EdError::CannotReadInput = err {
+                        main_loop::set_error_msg("Cannot read input file");
+                        show_warning(Some(&fname), "Cannot read input file");
+                    }
                 }
             }
         }
@@ -339,7 +493,16 @@ pub fn execute_command(buffer: &mut EdBuffer, command_line: &str) -> Result<(),
     }
     
     let command_char = clean_command.chars().next().unwrap_or('\0');
-    let command_args = if clean_command.len() > 1 { &clean_command[1..] } else { "" };
+    let command_args = if command_char == '!' {
+        // A shell command keeps its trailing blanks, which '!!' repeats
+        // (GNU ed main_loop.c get_shell_command)
+        let untrimmed = &extraction.remaining_command.trim_start()[1..];
+        untrimmed.strip_suffix('\n').unwrap_or(untrimmed)
+    } else if clean_command.len() > 1 {
+        &clean_command[1..]
+    } else {
+        ""
+    };
     
     // PHASE 3: Execute command (GNU ed exec_command)
     execute_ed_command(buffer, command_char, command_args, &extraction)
@@ -359,7 +522,7 @@ fn handle_empty_command(buffer: &mut EdBuffer, extraction: &main_loop::AddressEx
             // If there's a comma or other trailing chars, it's part of a larger command
             if extraction.remaining_command.trim().is_empty() {
                 if let Some(line) = buffer.get_line(addr) {
-                    println!("{}", line);
+                    outln!("{}", line);
                 }
             }
         } else {
@@ -367,18 +530,19 @@ fn handle_empty_command(buffer: &mut EdBuffer, extraction: &main_loop::AddressEx
         }
     } else {
         // No address - this is a bare newline command (GNU ed main_loop.c:739-742)
-        // Navigate to next line and print it: current_addr() + 1
+        // Navigate to next line and print it: current_addr() + 1, or the
+        // current line itself inside a global command list
         let current = buffer.current_line();
-        let next_line = current + 1;
+        let next_line = current + usize::from(traditional() || !main_loop::isglobal());
 
-        if next_line > buffer.len() {
+        if next_line == 0 || next_line > buffer.len() {
             // Trying to navigate past EOF - return error (GNU ed behavior)
             return Err(EdError::InvalidAddress);
         }
 
         buffer.set_current_line(next_line)?;
         if let Some(line) = buffer.get_line(next_line) {
-            println!("{}", line);
+            outln!("{}", line);
         }
     }
     Ok(())
@@ -390,57 +554,74 @@ fn execute_ed_command(
     command_args: &str,
     addresses: &main_loop::AddressExtraction
 ) -> Result<(), EdError> {
+    // Print flags of a command's p, l or n suffix (GNU ed get_command_suffix)
+    let mut pflags = 0;
     match command_char {
-        'p' => main_loop::execute_print_command(buffer, addresses),
+        'p' => {
+            // GNU ed main_loop.c:650 get_command_suffix - only l, n, p may follow
+            let pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_print_command(buffer, addresses, pflags)
+        },
         'q' => {
-            // Quit shouldn't have an address (GNU ed main_loop.c:667 unexpected_address)
-            if addresses.addr_count > 0 {
-                return Err(EdError::InvalidAddress);
-            }
+            // Quit shouldn't have an address (GNU ed main_loop.c:666 unexpected_address)
+            main_loop::unexpected_address(addresses.addr_count)?;
+            main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
             main_loop::execute_quit_command(buffer, false)
         },
         'Q' => {
             // Unconditional quit also shouldn't have an address
-            if addresses.addr_count > 0 {
-                return Err(EdError::InvalidAddress);
-            }
+            main_loop::unexpected_address(addresses.addr_count)?;
+            main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
             main_loop::execute_quit_command(buffer, true)
         },
         'a' => {
-            buffer.clear_undo_stack();
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            buffer.clear_undo_unless_global(main_loop::isglobal());
             main_loop::append_text_input(buffer, addresses)
         },
         'd' => {
-            buffer.clear_undo_stack();
+            // The range is checked before the suffix (GNU ed main_loop.c:580)
+            main_loop::get_address_range(buffer, addresses)?;
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            buffer.clear_undo_unless_global(main_loop::isglobal());
             main_loop::execute_delete_command(buffer, addresses)
         },
         'i' => {
-            buffer.clear_undo_stack();
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            buffer.clear_undo_unless_global(main_loop::isglobal());
             main_loop::insert_text_input(buffer, addresses)
         },
         'c' => {
-            buffer.clear_undo_stack();
-            main_loop::execute_change_command(buffer, addresses)
+            buffer.clear_undo_unless_global(main_loop::isglobal());
+            main_loop::execute_change_command(buffer, command_args, addresses)
         },
-        'l' => main_loop::execute_list_command(buffer, addresses),
-        'n' => main_loop::execute_number_command(buffer, addresses),
-        '=' => main_loop::execute_line_number_command(buffer, addresses),
-        'u' => main_loop::undo_last_operation(buffer),
-        's' => {
-            buffer.clear_undo_stack();
-            main_loop::execute_substitute_command(buffer, command_args, addresses)
+        'l' => {
+            let pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_list_command(buffer, addresses, pflags)
         },
-        'w' => {
-            buffer.clear_undo_stack();
-            main_loop::execute_write_command(buffer, command_args, addresses, false)
+        'n' => {
+            let pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_number_command(buffer, addresses, pflags)
         },
-        'W' => {
-            buffer.clear_undo_stack();
-            main_loop::execute_write_command(buffer, command_args, addresses, true)
+        '=' => {
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_line_number_command(buffer, addresses)
         },
+        'u' => {
+            // GNU ed main_loop.c:690
+            main_loop::unexpected_address(addresses.addr_count)?;
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::undo_last_operation(buffer)
+        },
+        's' => {
+            buffer.clear_undo_unless_global(main_loop::isglobal());
+            main_loop::execute_substitute_command(buffer, command_args, addresses)
+        },
+        'w' => main_loop::execute_write_command(buffer, command_args, addresses, false),
+        'W' => main_loop::execute_write_command(buffer, command_args, addresses, true),
         'r' => main_loop::execute_read_command(buffer, command_args, addresses),
-        'e' => main_loop::execute_edit_command(buffer, command_args),
-        'E' => main_loop::execute_edit_force(buffer, command_args),
+        'e' => main_loop::execute_edit_command(buffer, command_args, addresses),
+        'E' => main_loop::execute_edit_force(buffer, command_args, addresses),
         'f' => main_loop::execute_filename_command(buffer, command_args, addresses),
         '!' => main_loop::execute_shell_command_with_buffer(buffer, command_args, addresses),
         '1'..='9' | '0' => {
@@ -449,7 +630,7 @@ fn execute_ed_command(
                 if line_num > 0 && line_num <= buffer.len() {
                     buffer.set_current_line(line_num)?;
                     if let Some(line) = buffer.get_line(line_num) {
-                        println!("{}", line);
+                        outln!("{}", line);
                     }
                 } else {
                     return Err(EdError::InvalidAddress);
@@ -459,7 +640,7 @@ fn execute_ed_command(
             }
             Ok(())
         },
-        'j' => main_loop::execute_join_command(buffer, addresses),
+        'j' => main_loop::execute_join_command(buffer, command_args, addresses),
         'm' => main_loop::execute_move_command(buffer, command_args, addresses),
         't' => main_loop::execute_copy_command(buffer, command_args, addresses),
         'k' => main_loop::execute_mark_command(buffer, command_args, addresses),
@@ -470,12 +651,37 @@ fn execute_ed_command(
         'V' => main_loop::execute_global_command(buffer, command_args, addresses, false, true),   // match = false, interactive = true for 'V'
         '?' => main_loop::execute_backward_search(buffer, command_args, addresses),
         '/' => main_loop::execute_forward_search(buffer, command_args, addresses),
-        'h' => main_loop::execute_help_command(),
-        'H' => main_loop::execute_verbose_help_command(),
-        'P' => main_loop::execute_prompt_command(),
+        'h' => {
+            // h, H and P take no address (GNU ed main_loop.c:623, 666)
+            main_loop::unexpected_address(addresses.addr_count)?;
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_help_command()
+        },
+        'H' => {
+            main_loop::unexpected_address(addresses.addr_count)?;
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_verbose_help_command()
+        },
+        'P' => {
+            main_loop::unexpected_address(addresses.addr_count)?;
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_prompt_command()
+        },
         'z' => main_loop::execute_scroll_command(buffer, command_args, addresses),
-        'y' => main_loop::execute_yank_command(buffer, addresses),
-        'x' => main_loop::execute_put_command(buffer, addresses),
-        _ => Err(EdError::InvalidCommand),
-    }
+        'y' => {
+            main_loop::get_address_range(buffer, addresses)?;
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_yank_command(buffer, addresses)
+        },
+        'x' => {
+            pflags = main_loop::get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
+            main_loop::execute_put_command(buffer, addresses)
+        },
+        // Comment: the rest of the line is skipped, addresses and all (GNU ed main_loop.c:743)
+        '#' => Ok(()),
+        _ => Err(EdError::UnknownCommand),
+    }?;
+
+    // GNU ed main_loop.c:747 - a p, l or n suffix prints the new current line
+    main_loop::print_current_line(buffer, pflags)
 }
\ No newline at end of file
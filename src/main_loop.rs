@@ -20,12 +20,10 @@
 /// This file matches main_loop.c structure exactly for human review
 /// C source: main_loop.c (32,051 bytes) - IMMUTABLE REFERENCE
 
-use std::io::{self, Write};
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::io::{self, IsTerminal, Write};
 use crate::buffer::EdBuffer;
 use crate::error::EdError;
-use regex::{Regex, RegexBuilder};
+use regex::RegexBuilder;
 
 /// Address types moved from address.rs - these belong in main_loop.c according to GNU ed structure
 
@@ -62,10 +60,7 @@ impl AddressExtraction {
     }
 }
 
-// Static state matching main_loop.c
-// Global state converted to safe Rust - matches main_loop.c functionality
-static VERBOSE: AtomicBool = AtomicBool::new(false);
-static ERROR_MSG: Mutex<&'static str> = Mutex::new("");
+// main_loop.c statics (verbose, errmsg) live in session::Session
 
 /// first_e_command - matches main_loop.c:46
 pub fn first_e_command(filename: &str) -> i32 {
@@ -80,14 +75,12 @@ pub fn invalid_address() {
 
 /// error_msg - matches main_loop.c:64 (now memory safe)
 pub fn error_msg() -> &'static str {
-    ERROR_MSG.lock().map_or("", |guard| *guard)
+    crate::session::with(|s| s.error_msg)
 }
 
 /// set_error_msg - matches main_loop.c:66 (now memory safe)
 pub fn set_error_msg(msg: &'static str) {
-    if let Ok(mut guard) = ERROR_MSG.lock() {
-        *guard = msg;
-    }
+    crate::session::with(|s| s.error_msg = msg);
 }
 
 /// set_def_filename - matches main_loop.c:51
@@ -97,21 +90,69 @@ pub fn set_def_filename(s: &str) -> bool {
 }
 
 /// set_prompt - matches main_loop.c:72
+/// Setting a prompt string also turns the prompt on
 pub fn set_prompt(s: &str) -> bool {
-    // TODO: Implement prompt setting
+    crate::session::with(|session| {
+        session.prompt_str = std::borrow::Cow::Owned(s.to_string());
+        session.prompt_on = true;
+    });
     true
 }
 
 /// set_verbose - matches main_loop.c:85 (now memory safe)
 /// Toggles verbose mode (GNU ed: verbose = !verbose)
 pub fn set_verbose() {
-    let current = VERBOSE.load(Ordering::Relaxed);
-    VERBOSE.store(!current, Ordering::Relaxed);
+    crate::session::with(|s| s.verbose = !s.verbose);
 }
 
 /// verbose - check if verbose mode is enabled
 pub fn verbose() -> bool {
-    VERBOSE.load(Ordering::Relaxed)
+    crate::session::with(|s| s.verbose)
+}
+
+/// isglobal - check if a g/v/G/V command list is running
+pub fn isglobal() -> bool {
+    crate::session::with(|s| s.isglobal)
+}
+
+/// json_errors - report errors as JSON on stderr (not in C original)
+/// Enabled by RUST_ED_JSON_ERRORS=1; off by default to keep GNU ed output
+fn json_errors() -> bool {
+    std::env::var("RUST_ED_JSON_ERRORS").is_ok_and(|v| v.trim() == "1")
+}
+
+/// json_escape - quote an error message for a JSON string value
+fn json_escape(msg: &str) -> String {
+    let mut escaped = String::with_capacity(msg.len());
+    for ch in msg.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// read_command_line - read one command, at most max_len bytes long
+/// Returns None at EOF. A longer line is skipped through its newline and
+/// reported as LineTooLong so memory use stays bounded (not in C original)
+fn read_command_line<R: io::BufRead>(reader: &mut R, max_len: usize) -> Result<Option<String>, EdError> {
+    use std::io::{BufRead, Read};
+    let mut bytes = Vec::new();
+    let read = reader.by_ref().take(max_len as u64 + 1).read_until(b'\n', &mut bytes)
+        .map_err(|e| EdError::IoError(e.to_string()))?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if bytes.len() > max_len && bytes.last() != Some(&b'\n') {
+        reader.skip_until(b'\n').map_err(|e| EdError::IoError(e.to_string()))?;
+        return Err(EdError::LineTooLong);
+    }
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
 }
 
 /// mark_line_node - matches main_loop.c:91
@@ -137,6 +178,28 @@ fn skip_blanks(input: &str) -> &str {
     input.trim_start()
 }
 
+/// check_filename_length - the path_max test of get_filename (main_loop.c:197)
+/// Shell commands ('!') are not file names and are not checked
+fn check_filename_length(name: &str) -> Result<(), EdError> {
+    if !name.starts_with('!') && name.len() > EdBuffer::path_max(None) as usize {
+        return Err(EdError::FilenameTooLong);
+    }
+    Ok(())
+}
+
+/// check_file_operand - the checks get_filename makes on a typed name
+/// (main_loop.c:193-206): a shell command goes to get_shell_command, which
+/// refuses it in restricted mode; a file name to may_access_filename
+fn check_file_operand(name: &str) -> Result<(), EdError> {
+    if !name.starts_with('!') {
+        may_access_filename(name)
+    } else if crate::restricted() {
+        Err(EdError::ShellRestricted)
+    } else {
+        Ok(())
+    }
+}
+
 /// get_filename - matches main_loop.c:177
 /// Implements filename parsing with tilde expansion
 /// C source reference: main_loop.c:177-207
@@ -207,10 +270,32 @@ fn set_second_addr(addr: i32, addr_cnt: i32) -> bool {
     true
 }
 
+/// check_read_only - rust-ed extension: reject buffer mutations under -R/--read-only
+fn check_read_only() -> Result<(), EdError> {
+    if crate::read_only() {
+        return Err(EdError::ReadOnly);
+    }
+    Ok(())
+}
+
 /// get_command_suffix - matches main_loop.c:354
-fn get_command_suffix(ibufpp: &str) -> Option<String> {
-    // TODO: Implement command suffix parsing
-    None
+/// Returns the print flags (pf_n = 1, pf_l = 2, pf_p = 4) or None for "Invalid command suffix"
+pub fn get_command_suffix(ibufpp: &str) -> Option<i32> {
+    let mut pflags = 0;
+    for ch in ibufpp.chars() {
+        let flag = match ch {
+            'n' => 0x1,
+            'l' => 0x2,
+            'p' => 0x4,
+            _ => return None,
+        };
+        // A repeated flag ends the suffix in GNU ed, which then requires a newline
+        if (pflags & flag) != 0 {
+            return None;
+        }
+        pflags |= flag;
+    }
+    Some(pflags)
 }
 
 /// get_command_s_suffix - matches main_loop.c:373
@@ -219,16 +304,31 @@ fn get_command_s_suffix(ibufpp: &str) -> Option<String> {
     None
 }
 
+/// print_current_line - the print suffix step of exec_command (main_loop.c:747)
+/// A p, l or n suffix prints the line the command left current
+pub fn print_current_line(buffer: &mut EdBuffer, pflags: i32) -> Result<(), EdError> {
+    if pflags != 0 {
+        let current = buffer.current_line();
+        crate::io::print_lines(buffer, current, current, pflags)?;
+    }
+    Ok(())
+}
+
 /// unexpected_address - matches main_loop.c:404
-fn unexpected_address(addr_cnt: i32) -> bool {
-    // TODO: Implement unexpected address detection
-    false
+pub fn unexpected_address(addr_cnt: i32) -> Result<(), EdError> {
+    if addr_cnt > 0 {
+        return Err(EdError::UnexpectedAddress);
+    }
+    Ok(())
 }
 
 /// unexpected_command_suffix - matches main_loop.c:410
-fn unexpected_command_suffix(ch: char) -> bool {
-    // TODO: Implement unexpected suffix detection
-    false
+/// A filename must be separated from its command letter by a blank
+pub fn unexpected_command_suffix(command_args: &str) -> Result<(), EdError> {
+    match command_args.chars().next() {
+        Some(ch) if !ch.is_whitespace() => Err(EdError::UnexpectedCommandSuffix),
+        _ => Ok(()),
+    }
 }
 
 /// command_s - matches main_loop.c:418 (COMPLEX SUBSTITUTE)
@@ -273,31 +373,66 @@ fn exec_command(ibufpp: &str, isglobal: bool, buffer: &mut EdBuffer) -> i32 {
 pub fn main_loop(initial_error: bool, loose: bool, buffer: &mut EdBuffer) -> i32 {
     // TODO: Move main loop logic from main.rs run function
     
-    let mut had_error = initial_error;
+    // Exit status as GNU ed keeps it: 1 after a failed command, 2 when
+    // EOF leaves a modified buffer; -l keeps both at 0
+    let mut err_status = if initial_error { 1 } else { 0 };
+    // The last command ended with the "buffer modified" warning (status == EMOD)
+    let mut warned_last = false;
+    crate::signal::set_signals();
     
     loop {
-        // Print prompt if enabled (GNU ed main loop prints "*" when prompt_on)
+        // Print prompt if enabled (GNU ed main_loop.c:828), on stdout and
+        // flushed before the command is read; like fputs, escapes in it
+        // are printed as they are
         if crate::prompt_on() {
-            print!("*");
+            out!("{}", crate::session::with(|s| s.prompt_str.clone()));
             use std::io::Write;
             std::io::stdout().flush().unwrap_or(());
         }
 
-        // Read command line
-        let mut input = String::new();
-        match io::stdin().read_line(&mut input) {
-            Ok(0) => break, // EOF
-            Ok(_) => {},
+        // Read command line, refusing anything longer than the configured limit
+        let max_len = buffer.limits().max_command_length;
+        // The stdin lock must be released before the command runs, since
+        // a, c, i and friends read their text from stdin too
+        let line = read_command_line(&mut crate::signal::Interruptible(io::stdin().lock()), max_len);
+        if crate::signal::hangup_pending() {
+            crate::signal::sighup_handler(buffer);
+        }
+        if crate::signal::check_interrupt_jump().is_some() {
+            report_interrupt();
+            continue;
+        }
+        let result = match line {
+            Ok(Some(input)) => execute_command_wrapper(buffer, input.trim_start()),
+            // EOF quits like q, warning once about a modified buffer (main_loop.c:831-834)
+            Ok(None) if !buffer.modified() || warned_last => break,
+            Ok(None) => {
+                buffer.set_warned(true);
+                if !loose {
+                    err_status = 2;
+                }
+                Err(EdError::WarningUnsavedChanges)
+            }
+            Err(EdError::LineTooLong) => Err(EdError::LineTooLong),
             Err(_) => {
-                had_error = true;
+                err_status = 2;
                 break;
             }
+        };
+        warned_last = matches!(result, Err(EdError::WarningUnsavedChanges));
+
+        // A hangup during the command, or while it read text, ends the session
+        if crate::signal::hangup_pending() {
+            crate::signal::sighup_handler(buffer);
+        }
+        // An interrupted command stops where it was, with no error status
+        if crate::signal::check_interrupt_jump().is_some() {
+            report_interrupt();
+            continue;
         }
-        
-        let command_line = input.trim();
-        
+
         // Process command using GNU ed architecture
-        match execute_command_wrapper(buffer, command_line) {
+        match result {
             Ok(()) => {},
             Err(EdError::Quit) => break,
             Err(err) => {
@@ -306,29 +441,92 @@ pub fn main_loop(initial_error: bool, loose: bool, buffer: &mut EdBuffer) -> i32
                     EdError::NothingToPut => set_error_msg("Nothing to put"),
                     EdError::NothingToUndo => set_error_msg("Nothing to undo"),
                     EdError::InvalidAddress => set_error_msg("Invalid address"),
+                    EdError::UnexpectedAddress => set_error_msg("Unexpected address"),
                     EdError::InvalidCommand => set_error_msg("Invalid command"),
                     EdError::InvalidFilename => set_error_msg("Invalid filename"),
-                    EdError::PatternNotFound => set_error_msg("Pattern not found"),
+                    EdError::TooManyLines => set_error_msg("Too many lines in buffer"),
+                    EdError::ShellTimeout => set_error_msg("Shell command timed out"),
+                    EdError::CannotCreateShell => set_error_msg("Can't create shell process"),
+                    EdError::InvalidCommandSuffix => set_error_msg("Invalid command suffix"),
+                    EdError::UnexpectedCommandSuffix => set_error_msg("Unexpected command suffix"),
+                    EdError::UnknownCommand => set_error_msg("Unknown command"),
+                    EdError::ReadOnly => set_error_msg("Read-only mode"),
+                    EdError::UnexpectedEof => set_error_msg("Unexpected end-of-file"),
+                    EdError::NoPreviousPattern => set_error_msg("No previous pattern"),
+                    EdError::CannotOpenOutput => set_error_msg("Cannot open output file"),
+                    EdError::CannotWriteFile => set_error_msg("Cannot write file"),
+                    EdError::CannotCloseOutput => set_error_msg("Cannot close output file"),
+                    EdError::InvalidPattern(msg) => set_error_msg(msg),
+                    EdError::NoMatch => set_error_msg("No match"),
+                    EdError::LineTooLong => set_error_msg("Line too long"),
+                    EdError::InfiniteSubstitutionLoop => set_error_msg("Infinite substitution loop"),
+                    EdError::InvalidDestination => set_error_msg("Invalid destination"),
+                    EdError::NumberOutOfRange => set_error_msg("Number out of range"),
+                    EdError::NoPreviousCommand => set_error_msg("No previous command"),
+                    EdError::NoCurrentFilename => set_error_msg("No current filename"),
+                    EdError::ShellRestricted => set_error_msg("Shell access restricted"),
+                    EdError::DirectoryRestricted => set_error_msg("Directory access restricted"),
+                    EdError::CannotNestGlobal => set_error_msg("Cannot nest global commands"),
+                    EdError::InvalidMarkCharacter => set_error_msg("Invalid mark character"),
+                    EdError::FilenameTooLong => set_error_msg("Filename too long"),
+                    EdError::CannotReadInput => set_error_msg("Cannot read input file"),
+                    EdError::FileChangedOnDisk => set_error_msg("File changed on disk"),
+                    EdError::WarningUnsavedChanges => set_error_msg("Warning: buffer modified"),
                     _ => set_error_msg("Error"),
                 }
 
-                // Print "?" (GNU ed always prints this)
-                println!("?");
+                // Print "?" (GNU ed always prints this), or a JSON line for tooling
+                if json_errors() {
+                    eprintln!("{{\"error\":\"{}\",\"code\":\"{}\"}}", json_escape(error_msg()), err.name());
+                } else {
+                    outln!("?");
+                }
 
                 // If verbose mode, also print the error message
-                if verbose() {
+                if verbose() && !json_errors() {
                     let msg = error_msg();
                     if !msg.is_empty() {
-                        println!("{}", msg);
+                        outln!("{}", msg);
                     }
                 }
 
-                had_error = true;
+                if !loose && err_status == 0 {
+                    err_status = 1;
+                }
             }
         }
+
+        if let Some(status) = verbose_status(buffer, io::stderr().is_terminal()) {
+            eprintln!("{}", status);
+        }
     }
     
-    if had_error { 1 } else { 0 }
+    err_status
+}
+
+/// report_interrupt - what main_loop.c:821 does once a SIGINT jumps back
+/// to the main loop
+fn report_interrupt() {
+    outln!("\n?");
+    set_error_msg("Interrupt");
+    if verbose() {
+        outln!("{}", error_msg());
+    }
+}
+
+/// verbose_status - with H on, the status line an interactive user gets
+/// after each command; only on a terminal, so piped output stays identical
+/// to GNU ed (not in C original)
+fn verbose_status(buffer: &EdBuffer, on_terminal: bool) -> Option<String> {
+    (verbose() && on_terminal).then(|| status_line(buffer))
+}
+
+/// status_line - line count, current line and modified flag (not in C original)
+fn status_line(buffer: &EdBuffer) -> String {
+    format!("[{} lines, current {}, {}]",
+            buffer.last_addr(),
+            buffer.current_addr(),
+            if buffer.modified() { "modified" } else { "unmodified" })
 }
 
 /// get_filename - moved from buffer.rs to match C source structure in main_loop.c:177
@@ -338,13 +536,18 @@ pub fn get_filename_from_buffer(buffer: &EdBuffer) -> Option<&str> {
 }
 
 /// may_access_filename - moved from main.rs to match C source structure (main_loop.c)
-pub fn may_access_filename(name: &str) -> bool {
+/// GNU ed main.c:193 - in restricted mode a file name may not name another
+/// directory or, on the command line, a shell command
+pub fn may_access_filename(name: &str) -> Result<(), EdError> {
     // Moved from main.rs to match GNU ed C source structure (main_loop.c)
     // Implement restricted mode filename checking
     if super::restricted() {
+        if name.starts_with('!') {
+            return Err(EdError::ShellRestricted);
+        }
         // In restricted mode, only allow files in current directory
-        if name.contains('/') || name.starts_with("..") {
-            return false;
+        if name == ".." || name.contains('/') {
+            return Err(EdError::DirectoryRestricted);
         }
     }
     
@@ -353,11 +556,11 @@ pub fn may_access_filename(name: &str) -> bool {
         for ch in name.chars() {
             if (ch as u32 >= 1 && ch as u32 <= 31) || ch as u32 == 127 {
                 // set_error_msg("Control characters not allowed in file names");
-                return false;
+                return Err(EdError::InvalidFilename);
             }
         }
     }
-    true
+    Ok(())
 }
 
 /// get_line_node_addr - moved from buffer.rs to match C source structure (main_loop.c)
@@ -398,50 +601,25 @@ pub fn get_line_node_addr(buffer: &crate::buffer::EdBuffer, line_num: usize) ->
 /// Command execution functions - moved from main.rs to match main_loop.c structure
 
 /// execute_print_command - moved from main.rs (case 'p' in main_loop.c:648)
-pub fn execute_print_command(buffer: &EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+/// pflags are the suffix flags, e.g. 'pn' numbers the lines
+pub fn execute_print_command(buffer: &mut EdBuffer, addresses: &AddressExtraction, pflags: i32) -> Result<(), EdError> {
     let (start, end) = get_address_range(buffer, addresses)?;
-    
-    for line_num in start..=end {
-        if let Some(line) = buffer.get_line(line_num) {
-            println!("{}", line);
-        }
-    }
+    crate::io::print_lines(buffer, start, end, pflags)?;
     Ok(())
 }
 
 /// execute_delete_command - moved from main.rs (case 'd' in main_loop.c:580)
 pub fn execute_delete_command(buffer: &mut crate::buffer::EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     let (start, end) = get_address_range(buffer, addresses)?;
-    
-    // Delete lines in reverse order to maintain line numbers
-    for line_num in (start..=end).rev() {
-        buffer.delete_line(line_num)?;
-    }
+    buffer.delete_lines(start, end, isglobal())?;
     Ok(())
 }
 
-/// escape_special_chars - moved from main.rs (helper for list command)
-pub fn escape_special_chars(line: &str) -> String {
-    line.chars().map(|c| match c {
-        '$' => "\\$".to_string(),
-        '\\' => "\\\\".to_string(),
-        '\t' => "\\t".to_string(),
-        '\n' => "\\n".to_string(),
-        '\r' => "\\r".to_string(),
-        c if c.is_control() => format!("\\{:03o}", c as u8),
-        c => c.to_string(),
-    }).collect()
-}
-
 /// execute_list_command - moved from main.rs (case 'l' in main_loop.c:646)
-pub fn execute_list_command(buffer: &EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+pub fn execute_list_command(buffer: &mut EdBuffer, addresses: &AddressExtraction, pflags: i32) -> Result<(), EdError> {
     let (start, end) = get_address_range(buffer, addresses)?;
-    
-    for line_num in start..=end {
-        if let Some(line) = buffer.get_line(line_num) {
-            println!("{}$", escape_special_chars(line));
-        }
-    }
+    crate::io::print_lines(buffer, start, end, pflags | 0x2)?; // pf_l
     Ok(())
 }
 
@@ -449,283 +627,133 @@ pub fn execute_list_command(buffer: &EdBuffer, addresses: &AddressExtraction) ->
 /// This function corresponds to address parsing in main_loop.c
 pub fn extract_addresses(command_line: &str, buffer: &crate::buffer::EdBuffer) -> Result<AddressExtraction, EdError> {
     let mut first_addr = -1i32;
-    let mut second_addr = -1i32;  
-    let mut addr_count = 0;
-    let mut chars = command_line.char_indices().peekable();
+    let mut second_addr = -1i32;
+    let mut first = true;  // true == addr, false == offset (GNU ed main_loop.c:234)
+    let last_addr = buffer.len() as i32;
+    // ';' makes the left address current for the rest of the line (main_loop.c:290)
+    let mut current_addr = buffer.current_line() as i32;
+    let bytes = command_line.as_bytes();
     let mut pos = 0;
-    
-    // Skip leading blanks (GNU ed skip_blanks)
-    while let Some(&(idx, ch)) = chars.peek() {
-        if ch.is_whitespace() {
-            chars.next();
-            pos = idx + ch.len_utf8();
-        } else {
-            break;
+
+    // Parse a run of digits starting at pos (GNU ed parse_int)
+    let parse_int = |pos: &mut usize| -> Result<i32, EdError> {
+        let start = *pos;
+        while *pos < bytes.len() && bytes[*pos].is_ascii_digit() {
+            *pos += 1;
         }
-    }
-    
-    // Parse addresses (simplified version of GNU ed logic)
-    while let Some((_idx, ch)) = chars.peek().copied() {
+        command_line[start..*pos].parse::<i32>().map_err(|_| EdError::NumberOutOfRange)
+    };
+    // Add an offset to an address; GNU ed's int sum wraps to a negative,
+    // hence invalid, address, so an overflow is reported as one
+    let offset = |addr: i32, n: i32| addr.checked_add(n).ok_or(EdError::InvalidAddress);
+
+    loop {
+        let ch = bytes.get(pos).copied().unwrap_or(b'\n');
         match ch {
-            // Numeric address
-            '0'..='9' => {
-                let mut num_str = String::new();
-                while let Some((_, digit_ch)) = chars.peek() {
-                    if digit_ch.is_ascii_digit() {
-                        num_str.push(*digit_ch);
-                        chars.next();
-                        pos += 1;
-                    } else {
-                        break;
-                    }
-                }
-                
-                if let Ok(addr) = num_str.parse::<i32>() {
-                    if first_addr == -1 {
-                        first_addr = addr;
-                        second_addr = addr;
-                        addr_count = 1;
-                    } else {
-                        first_addr = second_addr;
-                        second_addr = addr;
-                        addr_count = 2;
-                    }
+            b'0'..=b'9' => {
+                let n = parse_int(&mut pos)?;
+                if first {
+                    first = false;
+                    second_addr = n;
+                } else {
+                    // A number after an address is an offset: "2 3" == 5 (main_loop.c:246)
+                    second_addr = offset(second_addr, n)?;
                 }
             },
+            b' ' | b'\t' => pos += 1,
             // Relative address (+ or -) - GNU ed main_loop.c:252-261
-            '+' | '-' => {
-                let is_plus = ch == '+';
-                chars.next(); // consume '+' or '-'
-                pos += 1;
-
-                // If first address, set second_addr to current_addr (GNU ed line 253)
-                if first_addr == -1 {
-                    let current_line = buffer.current_line() as i32;
-                    second_addr = current_line;
-                }
-
-                // Check if there's a digit after + or - (GNU ed line 254-257)
-                if let Some(&(_, next_ch)) = chars.peek() {
-                    if next_ch.is_ascii_digit() {
-                        // Parse the number (e.g., +5, -3)
-                        let mut num_str = String::new();
-                        while let Some((_, digit_ch)) = chars.peek() {
-                            if digit_ch.is_ascii_digit() {
-                                num_str.push(*digit_ch);
-                                chars.next();
-                                pos += 1;
-                            } else {
-                                break;
-                            }
-                        }
-
-                        if let Ok(offset) = num_str.parse::<i32>() {
-                            if is_plus {
-                                second_addr += offset;
-                            } else {
-                                second_addr -= offset;
-                            }
-                        }
-                    } else {
-                        // Just + or - without number means ±1 (GNU ed line 259-260)
-                        if is_plus {
-                            second_addr += 1;
-                        } else {
-                            second_addr -= 1;
-                        }
-                    }
-                } else {
-                    // No character after + or -, means ±1
-                    if is_plus {
-                        second_addr += 1;
-                    } else {
-                        second_addr -= 1;
-                    }
+            b'+' | b'-' => {
+                if first {
+                    first = false;
+                    second_addr = current_addr;
                 }
-
-                // Update address tracking (GNU ed sets first=false after processing address)
-                if first_addr == -1 {
-                    first_addr = second_addr;
-                    addr_count = 1;
+                if bytes.get(pos + 1).is_some_and(|c| c.is_ascii_digit()) {
+                    pos += 1;
+                    let n = parse_int(&mut pos)?;
+                    second_addr = offset(second_addr, if ch == b'+' { n } else { -n })?;
                 } else {
-                    first_addr = second_addr;
-                    addr_count = 2;
+                    pos += 1;
+                    second_addr = offset(second_addr, if ch == b'+' { 1 } else { -1 })?;
                 }
             },
-            // Current line
-            '.' => {
-                chars.next();
-                pos += 1;
-                // Get actual current line number from buffer
-                let current_line = buffer.current_line() as i32;
-                if first_addr == -1 {
-                    first_addr = current_line;
-                    second_addr = current_line;
-                    addr_count = 1;
-                } else {
-                    first_addr = second_addr;
-                    second_addr = current_line;
-                    addr_count = 2;
+            // Current or last line (main_loop.c:262-266)
+            b'.' | b'$' => {
+                if !first {
+                    return Err(EdError::InvalidAddress);
                 }
-            },
-            // Last line
-            '$' => {
-                chars.next();
+                first = false;
                 pos += 1;
-                // Get actual last line number from buffer
-                let last_line = buffer.len() as i32;
-                if first_addr == -1 {
-                    first_addr = last_line;
-                    second_addr = last_line;
-                    addr_count = 1;
-                } else {
-                    first_addr = second_addr;
-                    second_addr = last_line;
-                    addr_count = 2;
-                }
+                second_addr = if ch == b'.' { current_addr } else { last_addr };
             },
-            // All lines address - GNU ed main_loop.c:277-290
-            // In GNU ed, % is treated same as , (both mean 1,$)
-            '%' | ',' => {
-                chars.next();
-                pos += 1;
-                // If no first address yet, % or , means "1,$" (all lines)
-                if first_addr == -1 {
-                    first_addr = 1;
-                    second_addr = buffer.len() as i32;
-                    addr_count = 2;
-                } else {
-                    // Comma after an address: shift addresses (GNU ed line 284)
-                    // first_addr = second_addr
-                    first_addr = second_addr;
-                    addr_count = 1; // We now have one address, waiting for potential second
-                    // Continue parsing - don't automatically extend to last line
-                    // Only if standalone "," at start means 1,$
+            // Forward or backward search (main_loop.c:267-271)
+            b'/' | b'?' => {
+                if !first {
+                    return Err(EdError::InvalidAddress);
                 }
-            },
-            // Range separator semicolon
-            ';' => {
-                chars.next();
-                pos += 1;
+                let remaining_input = &command_line[pos..];
+                let mut search_input = remaining_input;
+                second_addr = crate::regex::next_matching_node_addr_from(&mut search_input, buffer, current_addr as usize)? as i32;
+                pos += remaining_input.len() - search_input.len();
+                first = false;
             },
             // Mark address (GNU ed main_loop.c:272-276)
-            '\'' => {
-                chars.next(); // consume the '\''
+            b'\'' => {
+                if !first {
+                    return Err(EdError::InvalidAddress);
+                }
+                first = false;
                 pos += 1;
-
-                // Get the mark character (GNU ed: *(*ibufpp)++)
-                if let Some((_, mark_char)) = chars.next() {
-                    pos += 1;
-
-                    // Get the marked line address (GNU ed: get_marked_node_addr)
-                    match buffer.get_marked_node_addr(mark_char) {
-                        Ok(marked_line) => {
-                            let marked_addr = marked_line as i32;
-                            if first_addr == -1 {
-                                first_addr = marked_addr;
-                                second_addr = marked_addr;
-                                addr_count = 1;
-                            } else {
-                                first_addr = second_addr;
-                                second_addr = marked_addr;
-                                addr_count = 2;
-                            }
-                        },
-                        Err(_) => {
-                            // Invalid mark character or mark not set
-                            return Err(EdError::InvalidAddress);
-                        }
+                // Get the mark character (GNU ed: *(*ibufpp)++); at the end
+                // of the line GNU ed reads the newline, not a mark character
+                let mark_char = command_line[pos..].chars().next().unwrap_or('\n');
+                pos += mark_char.len_utf8();
+                second_addr = buffer.get_marked_node_addr(mark_char)? as i32;
+            },
+            // Range separators - GNU ed main_loop.c:277-294
+            b'%' | b',' | b';' => {
+                if first {
+                    if first_addr < 0 {
+                        // Leading separator: ',' and '%' mean 1,$ and ';' means .,$
+                        first_addr = if ch == b';' { current_addr } else { 1 };
+                        second_addr = last_addr;
+                    } else {
+                        first_addr = second_addr;
                     }
                 } else {
-                    // Missing mark character after '\''
-                    return Err(EdError::InvalidCommand);
-                }
-            },
-            // Forward search / (GNU ed main_loop.c:267-271)
-            '/' => {
-                // Parse forward search pattern - matches GNU ed logic exactly
-                let remaining_input = &command_line[pos..];
-                let mut search_input = remaining_input;
-
-                match crate::regex::next_matching_node_addr_with_buffer(&mut search_input, buffer) {
-                    Ok(found_addr) => {
-                        let addr = found_addr as i32;
-                        if first_addr == -1 {
-                            first_addr = addr;
-                            second_addr = addr;
-                            addr_count = 1;
-                        } else {
-                            first_addr = second_addr;
-                            second_addr = addr;
-                            addr_count = 2;
-                        }
-
-                        // Update position to after the search pattern
-                        let consumed = remaining_input.len() - search_input.len();
-                        pos += consumed;
-
-                        // Advance chars iterator to match position
-                        while chars.peek().is_some() && pos > chars.peek().unwrap().0 {
-                            chars.next();
-                        }
-                    },
-                    _ => {
-                        // Search failed or pattern not found
-                        return Err(EdError::PatternNotFound);
+                    if second_addr < 0 || second_addr > last_addr {
+                        return Err(EdError::InvalidAddress);
                     }
-                }
-            },
-            // Backward search ? (GNU ed main_loop.c:267-271)
-            '?' => {
-                // Parse backward search pattern - matches GNU ed logic exactly
-                let remaining_input = &command_line[pos..];
-                let mut search_input = remaining_input;
-
-                match crate::regex::next_matching_node_addr_with_buffer(&mut search_input, buffer) {
-                    Ok(found_addr) => {
-                        let addr = found_addr as i32;
-                        if first_addr == -1 {
-                            first_addr = addr;
-                            second_addr = addr;
-                            addr_count = 1;
-                        } else {
-                            first_addr = second_addr;
-                            second_addr = addr;
-                            addr_count = 2;
-                        }
-
-                        // Update position to after the search pattern
-                        let consumed = remaining_input.len() - search_input.len();
-                        pos += consumed;
-
-                        // Advance chars iterator to match position
-                        while chars.peek().is_some() && pos > chars.peek().unwrap().0 {
-                            chars.next();
-                        }
-                    },
-                    _ => {
-                        // Search failed or pattern not found
-                        return Err(EdError::PatternNotFound);
+                    if ch == b';' {
+                        current_addr = second_addr;
                     }
+                    first_addr = second_addr;
+                    first = true;
                 }
+                pos += 1;
             },
-            _ => break,
+            _ => {
+                if !first && (second_addr < 0 || second_addr > last_addr) {
+                    return Err(EdError::InvalidAddress);
+                }
+                // Unset addresses stay -1; a single address is both ends of the range
+                let addr_count = if second_addr >= 0 {
+                    if first_addr >= 0 { 2 } else { 1 }
+                } else {
+                    0
+                };
+                if addr_count == 1 {
+                    first_addr = second_addr;
+                }
+                let remaining_command = command_line.get(pos..).unwrap_or("").to_string();
+                return Ok(AddressExtraction {
+                    first_addr,
+                    second_addr,
+                    addr_count,
+                    remaining_command,
+                });
+            }
         }
     }
-    
-    // Extract remaining command
-    let remaining_command = if pos < command_line.len() {
-        command_line[pos..].to_string()
-    } else {
-        String::new()
-    };
-    
-    Ok(AddressExtraction {
-        first_addr,
-        second_addr,
-        addr_count,
-        remaining_command,
-    })
 }
 
 /// get_address_range - moved from main.rs to main_loop.rs (core command processing)
@@ -752,14 +780,11 @@ pub fn get_address_range(buffer: &EdBuffer, addresses: &AddressExtraction) -> Re
     Ok((start, end))
 }
 
-// Shell command state - matches GNU ed static variables in get_shell_command
-static PREVIOUS_SHELL_COMMAND: Mutex<Option<String>> = Mutex::new(None);
-
 /// get_shell_command - matches GNU ed main_loop.c:120-167
 /// Parse and expand shell command, handling '!' repetition and '%' filename expansion
 fn get_shell_command(command_args: &str, buffer: &EdBuffer) -> Result<String, EdError> {
     if crate::restricted() {
-        return Err(EdError::InvalidCommand);
+        return Err(EdError::ShellRestricted);
     }
 
     let mut command = String::new();
@@ -767,19 +792,18 @@ fn get_shell_command(command_args: &str, buffer: &EdBuffer) -> Result<String, Ed
     let mut replacement = false;
 
     // Handle command repetition with '!!'
+    // A bare '!' is not an error: it runs an empty command, as in GNU ed
     if let Some('!') = chars.peek() {
         chars.next(); // consume the '!'
-        if let Ok(guard) = PREVIOUS_SHELL_COMMAND.lock() {
-            if let Some(ref prev_cmd) = *guard {
-                if !prev_cmd.is_empty() && (!crate::traditional() || prev_cmd.len() > 1) {
-                    command.push_str(prev_cmd);
-                    replacement = true;
-                } else {
-                    return Err(EdError::InvalidCommand);
-                }
-            } else {
-                return Err(EdError::InvalidCommand);
+        // The previous command is kept in the session (GNU ed: static shcmd)
+        // In traditional mode a previous empty command cannot be repeated
+        let previous = crate::session::with(|s| s.previous_shell_command.clone());
+        match previous {
+            Some(prev_cmd) if !prev_cmd.is_empty() && (!crate::traditional() || prev_cmd.len() > 1) => {
+                command.push_str(&prev_cmd);
+                replacement = true;
             }
+            _ => return Err(EdError::NoPreviousCommand),
         }
     } else {
         // Add '!' prefix if not present
@@ -790,15 +814,12 @@ fn get_shell_command(command_args: &str, buffer: &EdBuffer) -> Result<String, Ed
     while let Some(ch) = chars.next() {
         if ch == '%' {
             // Replace '%' with default filename
-            if let Some(filename) = get_filename_from_buffer(buffer) {
-                if !filename.is_empty() {
+            match get_filename_from_buffer(buffer) {
+                Some(filename) if !filename.is_empty() => {
                     command.push_str(&filename);
                     replacement = true;
-                } else {
-                    return Err(EdError::InvalidCommand);
                 }
-            } else {
-                return Err(EdError::InvalidCommand);
+                _ => return Err(EdError::NoCurrentFilename),
             }
         } else if ch == '\\' {
             // Handle escape sequences
@@ -816,14 +837,12 @@ fn get_shell_command(command_args: &str, buffer: &EdBuffer) -> Result<String, Ed
     }
 
     // Store command for future repetition
-    if let Ok(mut guard) = PREVIOUS_SHELL_COMMAND.lock() {
-        *guard = Some(command.clone());
-    }
+    crate::session::with(|s| s.previous_shell_command = Some(command.clone()));
 
-    // Print expanded command if replacement occurred (GNU ed behavior)
-    if replacement && !crate::scripted() {
+    // Print expanded command if replacement occurred, even under -s (GNU ed main_loop.c:164)
+    if replacement {
         crate::print_escaped(&command[1..], true); // Skip initial '!'
-        println!();
+        outln!();
         std::io::stdout().flush().unwrap_or(());
     }
 
@@ -854,19 +873,17 @@ pub fn execute_shell_command_with_buffer(buffer: &mut EdBuffer, command_args: &s
         execute_shell_escape(&full_command[1..]) // Skip '!' prefix
     } else {
         // Line filtering command - process lines through shell command
+        check_read_only()?;
         execute_shell_filter_with_buffer(&full_command[1..], addresses, buffer)
     }
 }
 
 /// execute_shell_escape - simple shell command execution (GNU ed main_loop.c:519-525)
 fn execute_shell_escape(command: &str) -> Result<(), EdError> {
-    use std::process::Command;
-
-    let status = Command::new("/bin/sh")
-        .arg("-c")
-        .arg(command)
-        .status()
-        .map_err(|_| EdError::InvalidCommand)?;
+    let mut child = crate::io::shell_command(command)
+        .spawn()
+        .map_err(|_| EdError::CannotCreateShell)?;
+    let (status, _, _) = crate::io::wait_child_output(&mut child, None, crate::io::shell_timeout())?;
 
     if !status.success() {
         return Err(EdError::InvalidCommand);
@@ -874,7 +891,7 @@ fn execute_shell_escape(command: &str) -> Result<(), EdError> {
 
     // Print "!" to indicate shell command completion (GNU ed behavior)
     if !crate::scripted() {
-        println!("!");
+        outln!("!");
     }
 
     Ok(())
@@ -966,18 +983,19 @@ fn execute_shell_filter_with_buffer(command: &str, addresses: &AddressExtraction
     // Write addressed lines to shell command (GNU ed line 539)
     // This prints the byte count of lines being filtered
     crate::io::write_file(&shell_command_with_redirect, "w", first_addr, second_addr, buffer)
-        .map_err(|_| {
+        .map_err(|e| {
             let _ = std::fs::remove_file(&temp_file);
-            EdError::InvalidCommand
+            match e {
+                EdError::ShellTimeout => e,
+                _ => EdError::InvalidCommand,
+            }
         })?;
 
     // Clear undo stack before modifying buffer (GNU ed line 540)
-    buffer.clear_undo_stack();
+    buffer.clear_undo_unless_global(isglobal());
 
     // Delete original lines (GNU ed delete_lines line 541-542)
-    for line_num in (first_addr..=second_addr).rev() {
-        buffer.delete_line(line_num)?;
-    }
+    buffer.delete_lines(first_addr, second_addr, isglobal())?;
 
     // Read filtered output from temp file (GNU ed line 543-544)
     // This prints the byte count of filtered content
@@ -1021,45 +1039,102 @@ fn execute_command_wrapper(buffer: &mut EdBuffer, command_line: &str) -> Result<
     Err(EdError::Quit)
 }
 
+/// write_and_quit - convenience for embedders (not in C original)
+/// Writes the whole buffer like a bare 'w' then quits like 'q', returning the
+/// exit code, like the 'wq' command (GNU ed main_loop.c:695) without going
+/// through the command parser.
+///
+/// ```ignore
+/// // rust-ed is a bin crate, so this example is not run as a doctest
+/// let mut buffer = EdBuffer::new();
+/// buffer.append_line("hello".to_string());
+/// assert_eq!(write_and_quit(&mut buffer, Some("out.txt"))?, 0);
+/// ```
+pub fn write_and_quit(buffer: &mut EdBuffer, filename: Option<&str>) -> Result<i32, EdError> {
+    // No addresses: write defaults to 1,$ (GNU ed main_loop.c:700-703)
+    let addresses = AddressExtraction {
+        first_addr: -1,
+        second_addr: -1,
+        addr_count: 0,
+        remaining_command: String::new(),
+    };
+    // The arguments are what follows 'w' on a command line, blank included
+    let args = filename.map_or(String::new(), |name| format!(" {}", name));
+    execute_write_command(buffer, &args, &addresses, false)?;
+
+    // A full write cleared the modified flag, so 'q' will not warn
+    match execute_quit_command(buffer, false) {
+        Err(EdError::Quit) => Ok(EdError::Quit.exit_code()),
+        Err(err) => Err(err),
+        Ok(()) => Ok(0),
+    }
+}
+
+/// get_text_line - next line of text for a, c or i; None at '.' or EOF
+/// matches buffer.c:112 append_lines: inside a global command the text comes
+/// from the rest of the command list, which may also end without a '.'
+fn get_text_line() -> Result<Option<String>, EdError> {
+    Ok(get_input_line()?.filter(|line| line != "."))
+}
+
+/// get_input_line - next line of input, or None at EOF; inside a global
+/// command the rest of the command list is the input
+fn get_input_line() -> Result<Option<String>, EdError> {
+    // A pending signal ends the input before the next line is read
+    if crate::signal::interrupt_pending() || crate::signal::hangup_pending() {
+        return Err(EdError::InvalidCommand);
+    }
+    if isglobal() {
+        return Ok(crate::session::with(|s| s.global_commands.pop_front()));
+    }
+    let mut input = String::new();
+    match io::BufRead::read_line(&mut crate::signal::Interruptible(io::stdin().lock()), &mut input) {
+        Ok(0) => Ok(None), // GNU ed buffer.c append_lines: EOF ends input like '.'
+        Ok(_) => Ok(Some(input.trim_end_matches('\n').to_string())),
+        Err(_) => Err(EdError::InvalidCommand),
+    }
+}
+
 pub fn append_text_input(buffer: &mut EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Append after the addressed line (GNU ed behavior main_loop.c:569)
     // GNU ed: append_lines( ibufpp, second_addr, false, isglobal )
     // If no address specified, append after current line
-    let append_after_addr = if addresses.second_addr > 0 {
+    // Address 0 is valid here: 0a adds text before the first line
+    let append_after_addr = if addresses.addr_count > 0 && addresses.second_addr >= 0 {
         addresses.second_addr as usize
     } else {
         buffer.current_line()
     };
+    // GNU ed buffer.c:121 - the address is current even if no text follows
+    buffer.set_current_addr(append_after_addr);
 
-    // Collect input lines until we see '.'
+    // Collect input lines until we see '.'; an interrupt keeps the lines
+    // already typed, as GNU ed adds each line as soon as it is read
     let mut lines_to_append = Vec::new();
-    loop {
-        let mut input = String::new();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let line = input.trim_end_matches('\n');
-                if line == "." {
-                    break;
-                }
-                lines_to_append.push(line.to_string());
-            },
-            Err(_) => return Err(EdError::InvalidCommand),
+    let result = loop {
+        match get_text_line() {
+            Ok(Some(line)) => lines_to_append.push(line),
+            Ok(None) => break Ok(()),
+            Err(err) => break Err(err),
         }
-    }
+    };
 
     // Append all lines at once using buffer.append_lines (GNU ed buffer.c append_lines)
     if !lines_to_append.is_empty() {
         buffer.append_lines(&lines_to_append, append_after_addr)?;
     }
 
-    Ok(())
+    result
 }
 
 pub fn insert_text_input(buffer: &mut EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Insert before the addressed line (GNU ed behavior)
     // If no address specified, insert before current line
-    let insert_addr = if addresses.first_addr > 0 {
-        addresses.first_addr as usize
+    // GNU ed: append_lines( ibufpp, second_addr, true, isglobal ); 0i inserts at the top
+    let insert_addr = if addresses.addr_count > 0 && addresses.second_addr >= 0 {
+        addresses.second_addr as usize
     } else {
         buffer.current_line()
     };
@@ -1067,22 +1142,14 @@ pub fn insert_text_input(buffer: &mut EdBuffer, addresses: &AddressExtraction) -
     // GNU ed: insert before the line, so we calculate the position
     // insert_pos is 0-based, insert_addr is 1-based
     let insert_pos = if insert_addr > 0 { insert_addr - 1 } else { 0 };
+    // GNU ed buffer.c:121 - the address is current even if no text follows
+    buffer.set_current_addr(insert_addr);
 
     let mut lines_inserted = 0;
-    loop {
-        let mut input = String::new();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let line = input.trim_end_matches('\n');
-                if line == "." {
-                    break;
-                }
-                // Insert at position, adjusting for previously inserted lines
-                buffer.insert_line(insert_pos + lines_inserted, line.to_string())?;
-                lines_inserted += 1;
-            },
-            Err(_) => return Err(EdError::InvalidCommand),
-        }
+    while let Some(line) = get_text_line()? {
+        // Insert at position, adjusting for previously inserted lines
+        buffer.insert_line(insert_pos + lines_inserted, line)?;
+        lines_inserted += 1;
     }
 
     // Set current line to the last inserted line (GNU ed behavior)
@@ -1093,9 +1160,11 @@ pub fn insert_text_input(buffer: &mut EdBuffer, addresses: &AddressExtraction) -
     Ok(())
 }
 
-pub fn execute_change_command(buffer: &mut EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+pub fn execute_change_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Change command: delete addressed lines, then insert new content
     // Following GNU ed main_loop.c:572-578: delete_lines() + append_lines()
+    let pflags = get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
 
     let (start, end) = get_address_range(buffer, addresses)?;
 
@@ -1103,37 +1172,27 @@ pub fn execute_change_command(buffer: &mut EdBuffer, addresses: &AddressExtracti
     let insert_position = start;
 
     // Step 1: Delete the addressed lines (like GNU ed delete_lines())
-    for line_num in (start..=end).rev() {
-        buffer.delete_line(line_num)?;
-    }
+    buffer.delete_lines(start, end, isglobal())?;
 
     // Step 2: Insert new content at the position where deletion occurred
     // This follows GNU ed append_lines() behavior for change command
     change_text_input(buffer, insert_position)?;
 
-    Ok(())
+    // GNU ed main_loop.c:747 - print the new current line for a p/l/n suffix
+    print_current_line(buffer, pflags)
 }
 
-pub fn change_text_input(buffer: &mut EdBuffer, insert_position: usize) -> Result<(), EdError> {
+pub fn change_text_input(buffer: &mut EdBuffer, insert_position: usize) -> Result<usize, EdError> {
     // Read lines until '.' and insert them at the specified position
+    // Returns the number of lines inserted
     let mut lines_inserted = 0;
 
-    loop {
-        let mut input = String::new();
-        match std::io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let line = input.trim_end_matches('\n');
-                if line == "." {
-                    break;
-                }
-                // Insert at position, adjusting for previously inserted lines
-                // Use saturating_sub to handle edge case where insert_position is 0
-                let actual_position = insert_position.saturating_sub(1) + lines_inserted;
-                buffer.insert_line(actual_position, line.to_string())?;
-                lines_inserted += 1;
-            },
-            Err(_) => return Err(EdError::InvalidCommand),
-        }
+    while let Some(line) = get_text_line()? {
+        // Insert at position, adjusting for previously inserted lines
+        // Use saturating_sub to handle edge case where insert_position is 0
+        let actual_position = insert_position.saturating_sub(1) + lines_inserted;
+        buffer.insert_line(actual_position, line)?;
+        lines_inserted += 1;
     }
 
     // Set current line to the last inserted line (GNU ed behavior)
@@ -1142,17 +1201,14 @@ pub fn change_text_input(buffer: &mut EdBuffer, insert_position: usize) -> Resul
         buffer.set_current_line(new_current)?;
     }
 
-    Ok(())
+    Ok(lines_inserted)
 }
 
-pub fn execute_number_command(buffer: &EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+pub fn execute_number_command(buffer: &mut EdBuffer, addresses: &AddressExtraction, pflags: i32) -> Result<(), EdError> {
     let (start, end) = get_address_range(buffer, addresses)?;
 
-    for line_num in start..=end {
-        if let Some(line) = buffer.get_line(line_num) {
-            println!("{}\t{}", line_num, line);  // GNU ed: printf( "%d\t", current_addr() ); - no width formatting
-        }
-    }
+    // GNU ed main_loop.c:647 - n is print_lines with pf_n; io::print_line does the "%d\t" prefix
+    crate::io::print_lines(buffer, start, end, pflags | 0x1)?;
     Ok(())
 }
 
@@ -1164,103 +1220,151 @@ pub fn execute_line_number_command(buffer: &EdBuffer, addresses: &AddressExtract
     } else {
         buffer.last_addr()
     };
-    println!("{}", line_num);
+    outln!("{}", line_num);
     Ok(())
 }
 
 pub fn undo_last_operation(buffer: &mut EdBuffer) -> Result<(), EdError> {
+    check_read_only()?;
     buffer.undo_last_operation()
 }
 
 pub fn execute_substitute_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Direct implementation of substitute command with proper buffer integration
     // Follows GNU ed main_loop.c:418-491 and regex.c:406-448
     let (start, end) = get_address_range(buffer, addresses)?;
 
-    // Parse substitute command: s/pattern/replacement/flags
-    if let Some(parsed_sub) = parse_substitute_command(command_args) {
-        // Compile the regex pattern with flags (GNU ed regex.c supports I flag)
-        let regex_pattern = match RegexBuilder::new(&parsed_sub.pattern)
-            .case_insensitive(parsed_sub.ignore_case)
-            .build() {
-            Ok(re) => re,
-            Err(_) => return Err(EdError::InvalidCommand),
-        };
-
-        let mut last_modified_line = None;
-
-        // Perform substitution on each line in the range
-        // GNU ed regex.c:415-444 - processes each line, tracking last modified
-        for line_addr in start..=end {
-            if let Some(line_content) = buffer.get_line(line_addr) {
-                let new_content = if parsed_sub.global {
-                    // Global substitution (replace all matches)
-                    regex_pattern.replace_all(line_content, parsed_sub.replacement.as_str()).to_string()
-                } else if let Some(n) = parsed_sub.count {
-                    // Nth occurrence substitution (GNU ed supports s/pattern/replacement/N)
-                    replace_nth_occurrence(&regex_pattern, line_content, &parsed_sub.replacement, n as usize)
-                } else {
-                    // Single substitution (replace first match)
-                    regex_pattern.replace(line_content, parsed_sub.replacement.as_str()).to_string()
-                };
-
-                // Only modify buffer if content actually changed
-                if new_content != line_content {
-                    // Replace the line in the buffer
-                    let _ = buffer.modify_line(line_addr, new_content);
-                    buffer.set_current_line(line_addr).ok();
-                    last_modified_line = Some(line_addr);
-                }
-            }
+    // 's' followed only by g, p, r or a count repeats the last substitution
+    // (GNU ed main_loop.c:433-463)
+    let (regex_pattern, replacement, snum, print) = if let Some(sflags) = parse_substitute_repeat(command_args)? {
+        let (regex, rbuf, mut snum, mut print) = crate::session::with(|s| {
+            (s.subst_regexp.clone(), s.rbuf.clone(), s.subst_snum, s.subst_print)
+        });
+        let mut regex = regex.ok_or(EdError::InvalidPattern(crate::regex::NO_PREV_SUBST))?;
+        if sflags.search_regex {
+            regex = crate::regex::last_regexp().ok_or(EdError::NoPreviousPattern)?;
+        }
+        if let Some(n) = sflags.count {
+            snum = n;
+        } else if sflags.toggle_global {
+            snum = i32::from(snum <= 0);
         }
+        print ^= sflags.toggle_print;
+        (regex, String::from_utf8_lossy(&rbuf).into_owned(), snum, print)
+    } else {
+        // Parse substitute command: s/pattern/replacement/flags
+        let parsed_sub = parse_substitute_command(command_args)?;
 
-        // GNU ed main_loop.c:747 - if pflags set, print current line
-        // The 'p' flag causes the last modified line to be printed
-        if parsed_sub.print && last_modified_line.is_some() {
-            if let Some(line_addr) = last_modified_line {
-                if let Some(line_content) = buffer.get_line(line_addr) {
-                    println!("{}", line_content);
-                }
+        // Compile the regex pattern with flags (GNU ed regex.c supports I flag)
+        // An empty pattern reuses the last search or substitution regex (regex.c:152)
+        let regex = if parsed_sub.pattern.is_empty() {
+            crate::regex::last_regexp().ok_or(EdError::NoPreviousPattern)?
+        } else {
+            match RegexBuilder::new(&parsed_sub.pattern)
+                .case_insensitive(parsed_sub.ignore_case)
+                .dot_matches_new_line(true)
+                .build() {
+                Ok(re) => re,
+                Err(err) => return Err(EdError::InvalidPattern(crate::regex::regex_error_msg(&err))),
             }
+        };
+        let snum = if parsed_sub.global { 0 } else { parsed_sub.count.unwrap_or(1) };
+        (regex, parsed_sub.replacement, snum, parsed_sub.print)
+    };
+    crate::regex::set_last_regexp(&regex_pattern);
+    crate::session::with(|s| {
+        s.subst_regexp = Some(regex_pattern.clone());
+        s.rbuf = replacement.clone().into_bytes();
+        s.subst_snum = snum;
+        s.subst_print = print;
+    });
+
+    let mut last_modified_line = None;
+    let binary = buffer.isbinary();
+
+    // Perform substitution on each line in the range
+    // GNU ed regex.c:415-444 - processes each line, tracking last modified;
+    // a replacement with newlines splits the line, pushing the rest of the
+    // range down
+    let mut line_addr = start;
+    for _ in start..=end {
+        let new_lines = match buffer.get_line(line_addr) {
+            Some(line_content) => crate::regex::line_replace(&regex_pattern, line_content, &replacement, snum, binary)?,
+            None => None,
+        };
+        if let Some(new_lines) = new_lines {
+            let mut new_lines = new_lines.into_iter();
+            let _ = buffer.modify_line(line_addr, new_lines.next().unwrap_or_default());
+            for new_line in new_lines {
+                buffer.insert_line(line_addr, new_line)?;
+                line_addr += 1;
+            }
+            buffer.set_current_line(line_addr).ok();
+            last_modified_line = Some(line_addr);
         }
+        line_addr += 1;
+    }
 
-        // GNU ed regex.c:445-446 - if no match found (and not in global), return error
-        if last_modified_line.is_none() {
-            return Err(EdError::NoMatch);
+    // GNU ed main_loop.c:747 - if pflags set, print current line
+    // The 'p' flag causes the last modified line to be printed
+    // Inside a global command a line without a match is not an error, so
+    // the current line is printed all the same
+    if print && (last_modified_line.is_some() || isglobal()) {
+        if let Some(line_content) = buffer.get_line(buffer.current_line()) {
+            outln!("{}", line_content);
         }
+    }
 
-        Ok(())
-    } else {
-        Err(EdError::InvalidCommand)
+    // GNU ed regex.c:445-446 - if no match found (and not in global), return error
+    if last_modified_line.is_none() && !isglobal() {
+        return Err(EdError::NoMatch);
     }
-}
 
-/// Replace the nth occurrence of a pattern in a string
-/// GNU ed supports s/pattern/replacement/N where N is the occurrence number
-pub fn replace_nth_occurrence(regex: &Regex, text: &str, replacement: &str, n: usize) -> String {
-    let mut result = String::new();
-    let mut last_match_end = 0;
-    let mut occurrence_count = 0;
+    Ok(())
+}
 
-    for mat in regex.find_iter(text) {
-        occurrence_count += 1;
+/// Suffixes of an 's' that repeats the last substitution
+struct SubstituteRepeat {
+    toggle_global: bool,
+    toggle_print: bool,
+    search_regex: bool,
+    count: Option<i32>,
+}
 
-        if occurrence_count == n {
-            // Found the nth occurrence - replace it
-            result.push_str(&text[last_match_end..mat.start()]);
-            result.push_str(replacement);
-            last_match_end = mat.end();
-        } else {
-            // Not the nth occurrence - keep original
-            result.push_str(&text[last_match_end..mat.end()]);
-            last_match_end = mat.end();
+/// parse_substitute_repeat - the sflags loop of command_s (main_loop.c:433-457)
+/// Returns None when the arguments start a new pattern instead
+fn parse_substitute_repeat(args: &str) -> Result<Option<SubstituteRepeat>, EdError> {
+    let mut sflags = SubstituteRepeat { toggle_global: false, toggle_print: false, search_regex: false, count: None };
+    let bytes = args.as_bytes();
+    let mut pos = 0;
+    let mut any = false;
+    while pos < bytes.len() {
+        let duplicate = match bytes[pos] {
+            b'1'..=b'9' => {
+                let start = pos;
+                while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                    pos += 1;
+                }
+                let n = args[start..pos].parse::<i32>().ok();
+                let duplicate = sflags.toggle_global || sflags.count.is_some() || n.is_none();
+                sflags.count = n;
+                pos -= 1;
+                duplicate
+            }
+            b'g' => std::mem::replace(&mut sflags.toggle_global, true) || sflags.count.is_some(),
+            b'p' => std::mem::replace(&mut sflags.toggle_print, true),
+            b'r' => std::mem::replace(&mut sflags.search_regex, true),
+            _ if !any => return Ok(None),
+            _ => true,
+        };
+        if duplicate {
+            return Err(EdError::InvalidCommandSuffix);
         }
+        any = true;
+        pos += 1;
     }
-
-    // Append any remaining text after the last match
-    result.push_str(&text[last_match_end..]);
-
-    result
+    Ok(Some(sflags))
 }
 
 /// Parse substitute command arguments: s/pattern/replacement/flags
@@ -1273,23 +1377,33 @@ struct SubstituteArgs {
     count: Option<i32>,
 }
 
-pub fn parse_substitute_command(args: &str) -> Option<SubstituteArgs> {
-    // Basic substitute parsing: s/pattern/replacement/flags
-    if args.is_empty() || !args.starts_with('/') {
-        return None;
-    }
-    
-    let parts: Vec<&str> = args[1..].splitn(3, '/').collect();
-    if parts.len() < 2 {
-        return None;
+pub fn parse_substitute_command(args: &str) -> Result<SubstituteArgs, EdError> {
+    // The first character is the delimiter, as in get_pattern_for_s (regex.c:170)
+    let delimiter = match args.chars().next() {
+        Some(c) if c != ' ' && c != '\n' => c,
+        _ => return Err(EdError::InvalidPattern(crate::regex::INV_PAT_DEL)),
+    };
+
+    let mut rest = &args[delimiter.len_utf8()..];
+    let raw_pattern = crate::regex::extract_pattern(&mut rest, delimiter)
+        .map_err(EdError::InvalidPattern)?;
+    if !rest.starts_with(delimiter) {
+        return Err(EdError::InvalidPattern(crate::regex::MIS_PAT_DEL));
     }
-    
-    let pattern = parts[0].to_string();
-    let replacement = parts[1].to_string();
-    let flags = if parts.len() > 2 { parts[2] } else { "" };
-    
+    rest = &rest[delimiter.len_utf8()..];
+
+    // An escaped delimiter in the pattern stands for the delimiter itself;
+    // keep the backslash when the delimiter is a regex metacharacter so
+    // that it still matches literally
+    let keep_escape = "\\.+*?()|[]{}^$".contains(delimiter);
+    let pattern = unescape_delimiter(&raw_pattern, delimiter, keep_escape);
+
+    let (replacement, flags) = extract_replacement(rest, delimiter)?;
+    // An omitted last delimiter prints the line (GNU ed main_loop.c:472)
+    let mut print = flags.is_none();
+    let flags = flags.unwrap_or_default();
+
     let mut global = false;
-    let mut print = false;
     let mut ignore_case = false;
     let mut count = None;
     
@@ -1307,7 +1421,7 @@ pub fn parse_substitute_command(args: &str) -> Option<SubstituteArgs> {
         }
     }
     
-    Some(SubstituteArgs {
+    Ok(SubstituteArgs {
         pattern,
         replacement,
         global,
@@ -1318,6 +1432,81 @@ pub fn parse_substitute_command(args: &str) -> Option<SubstituteArgs> {
 }
 
 
+/// extract_replacement - matches regex.c:270
+/// Returns the replacement and the rest of the command after its last
+/// delimiter, where the flags start, or None if that delimiter is omitted.
+/// A lone '%' reuses the last replacement. An escaped newline carries the
+/// replacement on to the next line of input; inside a global command
+/// newlines are unescaped, and it runs on while the command list has lines
+fn extract_replacement(rest: &str, delimiter: char) -> Result<(String, Option<String>), EdError> {
+    if let Some(after) = rest.strip_prefix('%') {
+        let omitted = after.is_empty()
+            && (!isglobal() || crate::session::with(|s| s.global_commands.is_empty()));
+        if after.starts_with(delimiter) || omitted {
+            // rbuf is set along with the substitution regex
+            if !crate::regex::subst_regex() {
+                return Err(EdError::InvalidPattern(crate::regex::NO_PREV_SUBST));
+            }
+            let previous = crate::session::with(|s| String::from_utf8_lossy(&s.rbuf).into_owned());
+            return Ok((previous, after.strip_prefix(delimiter).map(str::to_string)));
+        }
+    }
+
+    let mut raw_replacement = String::new();
+    let mut line = rest.to_string();
+    let flags = 'lines: loop {
+        let mut chars = line.char_indices();
+        while let Some((i, ch)) = chars.next() {
+            if ch == delimiter {
+                break 'lines Some(line[i + ch.len_utf8()..].to_string());
+            }
+            raw_replacement.push(ch);
+            if ch == '\\' {
+                match chars.next() {
+                    Some((_, escaped)) => raw_replacement.push(escaped),
+                    None if !isglobal() => {
+                        raw_replacement.push('\n');
+                        line = get_input_line()?.ok_or(EdError::UnexpectedEof)?;
+                        continue 'lines;
+                    }
+                    None => {}
+                }
+            }
+        }
+        let next = if isglobal() { get_input_line()? } else { None };
+        match next {
+            Some(next) => {
+                raw_replacement.push('\n');
+                line = next;
+            }
+            None => break None,
+        }
+    };
+    Ok((unescape_delimiter(&raw_replacement, delimiter, false), flags))
+}
+
+/// Remove the backslash from escaped delimiters, leaving other escapes intact
+fn unescape_delimiter(text: &str, delimiter: char, keep_escape: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some(escaped) if escaped == delimiter && !keep_escape => result.push(escaped),
+            Some(escaped) => {
+                result.push(ch);
+                result.push(escaped);
+            }
+            None => result.push(ch),
+        }
+    }
+    result
+}
+
+
 pub fn execute_write_append(_buffer: &EdBuffer) -> Result<(), EdError> {
     // TODO: Implement write-append command
     Ok(())
@@ -1327,7 +1516,13 @@ pub fn execute_write_command(buffer: &mut EdBuffer, command_args: &str, addresse
     // Write command implementation following GNU ed main_loop.c:694-712
     // Two modes: 'w' (overwrite) and 'W' (append)
 
+    // GNU ed main_loop.c:695-697 - 'wq' and 'wQ' quit after writing
+    let quit = command_args.chars().next().filter(|&c| c == 'q' || c == 'Q');
+    let command_args = if quit.is_some() { &command_args[1..] } else { command_args };
+    unexpected_command_suffix(command_args)?;
+
     let filename = command_args.trim();
+    check_filename_length(filename)?;
 
     // Set default address range if none specified (GNU ed line 700-703)
     let (first_addr, second_addr) = if addresses.addr_count == 0 && buffer.len() == 0 {
@@ -1344,23 +1539,37 @@ pub fn execute_write_command(buffer: &mut EdBuffer, command_args: &str, addresse
     let target_filename = if filename.is_empty() {
         // Use default filename (GNU ed behavior)
         match get_filename_from_buffer(buffer) {
-            Some(fname) => fname,
+            Some(fname) => fname.to_string(),
             None => return Err(EdError::InvalidFilename),
         }
     } else {
         // Validate filename (GNU ed may_access_filename logic)
-        // Note: Shell commands start with '!' are validated inside io::write_file
-        if !filename.starts_with('!') && !may_access_filename(filename) {
-            return Err(EdError::InvalidFilename);
-        }
-        filename
+        check_file_operand(filename)?;
+        filename.to_string()
     };
+    let target_filename = target_filename.as_str();
+
+    // Read-only mode may write elsewhere but never back to the file being edited
+    if get_filename_from_buffer(buffer) == Some(target_filename) {
+        check_read_only()?;
+    }
+
+    // --safe-write: refuse the first 'w' over a file changed since it was read.
+    // The new state is recorded, so repeating the 'w' writes anyway.
+    if crate::safe_write() && !append && buffer.changed_on_disk(target_filename) {
+        buffer.record_disk_state(target_filename);
+        return Err(EdError::FileChangedOnDisk);
+    }
 
     // Use io::write_file which handles shell commands (io.c:287 detects '!')
     let write_mode = if append { "a" } else { "w" };
     match crate::io::write_file(target_filename, write_mode, first_addr, second_addr, buffer) {
         Ok(_bytes) => {
             // io::write_file already prints byte count
+            // Our own write is not an outside change (--safe-write)
+            if get_filename_from_buffer(buffer) == Some(target_filename) {
+                buffer.record_disk_state(target_filename);
+            }
 
             // If we wrote the entire buffer, mark buffer as unmodified
             // GNU ed behavior: writing full buffer to ANY file clears modified flag
@@ -1379,20 +1588,30 @@ pub fn execute_write_command(buffer: &mut EdBuffer, command_args: &str, addresse
             // This is true even if writing to a different filename!
             if wrote_entire_buffer && !target_filename.starts_with('!') {
                 buffer.set_modified(false);
+            } else if quit == Some('q') && buffer.is_modified() && !buffer.warned() {
+                // GNU ed main_loop.c:710 - 'wq' of part of the buffer warns like 'q'
+                buffer.set_warned(true);
+                return Err(EdError::WarningUnsavedChanges);
             }
 
-            Ok(())
+            match quit {
+                Some(_) => Err(EdError::Quit),
+                None => Ok(()),
+            }
         }
         Err(err) => Err(err)
     }
 }
 
 pub fn execute_read_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // r command: read file content after specified address
     // From GNU ed main_loop.c:670-680
+    unexpected_command_suffix(command_args)?;
 
     // Get filename (GNU ed line 672: fnp = get_filename())
     let filename_arg = command_args.trim();
+    check_filename_length(filename_arg)?;
     let filename_to_read = if filename_arg.is_empty() {
         // Use default filename (GNU ed line 677: fnp[0] ? fnp : def_filename)
         match get_filename_from_buffer(buffer) {
@@ -1400,10 +1619,11 @@ pub fn execute_read_command(buffer: &mut EdBuffer, command_args: &str, addresses
             None => return Err(EdError::InvalidFilename),
         }
     } else {
+        check_file_operand(filename_arg)?;
         filename_arg.to_string()
     };
 
-    // If no address given, read after last line (GNU ed line 671)
+    // If no address given, read after the last line, not the current one (GNU ed line 671)
     // Read command allows address 0 (insert at beginning)
     let insert_after_line = if addresses.has_no_addresses() {
         buffer.len()  // Default: after last line
@@ -1418,7 +1638,7 @@ pub fn execute_read_command(buffer: &mut EdBuffer, command_args: &str, addresses
     };
 
     // Clear undo stack before read operation (GNU ed line 676)
-    buffer.clear_undo_stack();
+    buffer.clear_undo_unless_global(isglobal());
 
     // Read file content using io::read_file which handles shell commands
     // GNU ed io.c:677 calls read_file which detects '!' and uses popen()
@@ -1439,7 +1659,7 @@ pub fn execute_read_command(buffer: &mut EdBuffer, command_args: &str, addresses
     }
 }
 
-pub fn execute_edit_command(buffer: &mut EdBuffer, command_args: &str) -> Result<(), EdError> {
+pub fn execute_edit_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
     // GNU ed main_loop.c:586 - 'e' command with modification check
     // if( modified() && !warned() ) return EMOD;
     if buffer.is_modified() && !buffer.warned() {
@@ -1447,6 +1667,10 @@ pub fn execute_edit_command(buffer: &mut EdBuffer, command_args: &str) -> Result
         return Err(EdError::WarningUnsavedChanges);
     }
 
+    // GNU ed main_loop.c:587 - falls through to E's address check
+    unexpected_address(addresses.addr_count)?;
+    unexpected_command_suffix(command_args)?;
+
     // Reset warned flag since we're proceeding with edit
     buffer.set_warned(false);
 
@@ -1460,9 +1684,17 @@ pub fn edit_file(buffer: &mut EdBuffer, filename: Option<&str>) -> Result<(), Ed
     // Note: 'e' calls execute_edit_command which checks modified/warned first
     // 'E' (execute_edit_force) calls this directly, skipping the check
 
-    // Clear buffer (GNU ed delete_lines(1, last_addr()))
+    // GNU ed main_loop.c:589 reads the file name before emptying the buffer
+    check_filename_length(filename.unwrap_or("").trim())?;
+    if let Some(fname) = filename.map(str::trim).filter(|f| !f.is_empty()) {
+        check_file_operand(&get_filename(fname).unwrap_or_else(|| fname.to_string()))?;
+    }
+
+    // Clear buffer (GNU ed delete_lines(1, last_addr()), close_sbuf, open_sbuf)
     buffer.clear_buffer();
+    buffer.close_sbuf();
     buffer.set_modified(false);
+    buffer.open_sbuf();
 
     // Determine filename to load (GNU ed line 596)
     let file_to_load: String = if let Some(fname) = filename {
@@ -1500,17 +1732,24 @@ pub fn edit_file(buffer: &mut EdBuffer, filename: Option<&str>) -> Result<(), Ed
     match crate::io::read_file(&file_to_load, 0, buffer) {
         Ok(_lines_read) => {
             // io::read_file already prints byte count
+            buffer.record_disk_state(&file_to_load);
             // Clear modified flag after successful edit (GNU ed behavior)
             // Edit command loads a clean state - buffer should not be marked modified
             buffer.set_modified(false);
+
+            // GNU ed main_loop.c:598: reset_undo_state() to prevent undoing the read
+            // Without this, 'u' after a bare 'E' (revert) would remove the reloaded lines
+            buffer.reset_undo_state();
             Ok(())
         },
         Err(err) => Err(err),
     }
 }
 
-pub fn execute_edit_force(buffer: &mut EdBuffer, command_args: &str) -> Result<(), EdError> {
+pub fn execute_edit_force(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
     // E command: same as e but doesn't check if modified (GNU ed line 587)
+    unexpected_address(addresses.addr_count)?;
+    unexpected_command_suffix(command_args)?;
     edit_file(buffer, Some(command_args))
 }
 
@@ -1518,22 +1757,20 @@ pub fn execute_filename_command(buffer: &mut EdBuffer, command_args: &str, addre
     // Implementation following GNU ed main_loop.c:600-608
 
     // Line 600: Check for unexpected address (GNU ed unexpected_address(addr_cnt))
-    if addresses.addr_count > 0 {
-        return Err(EdError::InvalidAddress);
-    }
+    unexpected_address(addresses.addr_count)?;
 
-    // Line 601: Check for unexpected command suffix - already handled by argument parsing
+    // Line 601: Check for unexpected command suffix
+    unexpected_command_suffix(command_args)?;
 
     // Line 602: Get filename using get_filename logic with tilde expansion
     // This matches main_loop.c:194-196 tilde expansion behavior
+    check_filename_length(command_args.trim())?;
     if let Some(filename) = get_filename(command_args) {
         // Line 604-605: Check for invalid redirection handled in get_filename
 
         // Line 606: Validate and set filename
         // Validate filename using GNU ed may_access_filename logic
-        if !may_access_filename(&filename) {
-            return Err(EdError::InvalidFilename);
-        }
+        may_access_filename(&filename)?;
 
         // Set the new default filename (GNU ed set_def_filename)
         buffer.set_filename(filename);
@@ -1547,12 +1784,13 @@ pub fn execute_filename_command(buffer: &mut EdBuffer, command_args: &str, addre
 
     // Print with escaping and newline (GNU ed behavior)
     crate::print_escaped(current_filename, true);
-    println!(); // GNU ed: putchar('\n')
+    outln!(); // GNU ed: putchar('\n')
 
     Ok(())
 }
 
-pub fn execute_join_command(buffer: &mut EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+pub fn execute_join_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Join command implementation following GNU ed main_loop.c:634-639
     // From GNU ed source: "case 'j': if( !set_addr_range( current_addr(), current_addr() + 1, addr_cnt ) ||"
 
@@ -1572,9 +1810,10 @@ pub fn execute_join_command(buffer: &mut EdBuffer, addresses: &AddressExtraction
     if second_addr > buffer.len() {
         return Err(EdError::InvalidAddress);
     }
+    let pflags = get_command_suffix(command_args).ok_or(EdError::InvalidCommandSuffix)?;
 
     // Step 3: Clear undo stack before operation (GNU ed line 636)
-    buffer.clear_undo_stack();
+    buffer.clear_undo_unless_global(isglobal());
 
     // Step 4: Only join if first_addr < second_addr (GNU ed line 637-638)
     if first_addr < second_addr {
@@ -1585,67 +1824,61 @@ pub fn execute_join_command(buffer: &mut EdBuffer, addresses: &AddressExtraction
         buffer.set_current_line(first_addr)?;
     }
 
-    Ok(())
+    // GNU ed main_loop.c:747 - print the new current line for a p/l/n suffix
+    print_current_line(buffer, pflags)
 }
 
 /// Parse destination address for move/copy commands (GNU ed get_third_addr logic)
-pub fn parse_destination_address(addr_str: &str, buffer: &EdBuffer) -> Result<usize, EdError> {
+/// Returns the destination and the print flags of the suffix that follows it
+pub fn parse_destination_address(addr_str: &str, buffer: &EdBuffer) -> Result<(usize, i32), EdError> {
     // Implementation following GNU ed get_third_addr() from main_loop.c:311-325
     // Uses extract_addresses to parse the destination address properly
+    let extraction = extract_addresses(addr_str, buffer).map_err(|_| EdError::InvalidAddress)?;
+    let pflags = get_command_suffix(&extraction.remaining_command).ok_or(EdError::InvalidCommandSuffix)?;
 
-    if addr_str.is_empty() {
+    if extraction.addr_count == 0 {
         // GNU ed: traditional() && addr_cnt == 0 - "Destination expected"
         if crate::traditional() {
             return Err(EdError::InvalidAddress);
         }
         // Default to current address if no destination specified
-        return Ok(buffer.current_line());
+        return Ok((buffer.current_line(), pflags));
     }
 
-    // Use existing address extraction logic to parse the destination
-    // This matches GNU ed's get_third_addr which calls extract_addresses
-    match extract_addresses(addr_str, buffer) {
-        Ok(extraction) => {
-            if extraction.second_addr < 0 {
-                return Err(EdError::InvalidAddress);
-            }
-            let dest_addr = extraction.second_addr as usize;
-
-            // GNU ed validation: second_addr < 0 || second_addr > last_addr()
-            if dest_addr > buffer.len() {
-                return Err(EdError::InvalidAddress);
-            }
-
-            Ok(dest_addr)
-        },
-        Err(_) => Err(EdError::InvalidAddress),
+    // GNU ed validation: second_addr < 0 || second_addr > last_addr()
+    if extraction.second_addr < 0 || extraction.second_addr as usize > buffer.len() {
+        return Err(EdError::InvalidAddress);
     }
+    Ok((extraction.second_addr as usize, pflags))
 }
 
 pub fn execute_move_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Move command implementation following GNU ed main_loop.c:655-663
 
     // Step 1: Validate source address range (GNU ed set_addr_range2)
     let (first_addr, second_addr) = get_address_range(buffer, addresses)?;
 
     // Step 2: Parse destination address from command_args (GNU ed get_third_addr)
-    let dest_addr = parse_destination_address(command_args.trim(), buffer)?;
+    let (dest_addr, pflags) = parse_destination_address(command_args.trim(), buffer)?;
 
     // Step 3: Validate destination not within source range (GNU ed line 657-658)
     if dest_addr >= first_addr && dest_addr < second_addr {
-        return Err(EdError::InvalidAddress);
+        return Err(EdError::InvalidDestination);
     }
 
     // Step 4: Clear undo stack before operation (GNU ed line 660)
-    buffer.clear_undo_stack();
+    buffer.clear_undo_unless_global(isglobal());
 
     // Step 5: Perform the move operation (GNU ed line 661)
     buffer.move_lines(first_addr, second_addr, dest_addr, false)?;
 
-    Ok(())
+    // GNU ed main_loop.c:747 - print the new current line for a p/l/n suffix
+    print_current_line(buffer, pflags)
 }
 
 pub fn execute_copy_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // Transfer command implementation following GNU ed main_loop.c:684-689
     // Syntax: [addr1,addr2]t[addr3] - copy lines from addr1-addr2 to after addr3
 
@@ -1653,17 +1886,18 @@ pub fn execute_copy_command(buffer: &mut EdBuffer, command_args: &str, addresses
     let (first_addr, second_addr) = get_address_range(buffer, addresses)?;
 
     // Step 2: Parse destination address from command_args (GNU ed get_third_addr)
-    let dest_addr = parse_destination_address(command_args.trim(), buffer)?;
+    let (dest_addr, pflags) = parse_destination_address(command_args.trim(), buffer)?;
 
     // Step 3: Clear undo stack before operation (GNU ed line 687)
-    buffer.clear_undo_stack();
+    buffer.clear_undo_unless_global(isglobal());
 
     // Step 4: Perform the copy operation (GNU ed copy_lines)
     // Convert from 1-based addressing (GNU ed) to 0-based for buffer.copy_lines
     // Note: dest_addr in GNU ed is where to copy AFTER, buffer.copy_lines expects where to insert
     buffer.copy_lines(first_addr, second_addr, dest_addr)?;
 
-    Ok(())
+    // GNU ed main_loop.c:747 - print the new current line for a p/l/n suffix
+    print_current_line(buffer, pflags)
 }
 
 pub fn execute_mark_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
@@ -1671,10 +1905,9 @@ pub fn execute_mark_command(buffer: &mut EdBuffer, command_args: &str, addresses
     // Syntax: ka - mark current line with 'a', 2ka - mark line 2 with 'a'
 
     // Step 1: Get the mark character from command_args (GNU ed: n = *(*ibufpp)++)
-    let mark_char = command_args.trim().chars().next().unwrap_or('\0');
-    if mark_char == '\0' {
-        return Err(EdError::InvalidCommand);
-    }
+    // It is whatever comes right after 'k', so in 'k a' the blank is the mark
+    // and 'a' a bad suffix; a missing one is reported after the address is checked
+    let mark_char = command_args.chars().next().unwrap_or('\0');
 
     // Step 2: Determine the line to mark (GNU ed: second_addr logic)
     let line_to_mark = if addresses.addr_count > 0 {
@@ -1692,11 +1925,18 @@ pub fn execute_mark_command(buffer: &mut EdBuffer, command_args: &str, addresses
     if line_to_mark == 0 {
         return Err(EdError::InvalidAddress);
     }
+    // With no mark character GNU ed takes the newline as one and then finds
+    // the line has no end: get_command_suffix fails
+    if mark_char == '\0' {
+        return Err(EdError::InvalidCommandSuffix);
+    }
+
+    let pflags = get_command_suffix(&command_args[mark_char.len_utf8()..]).ok_or(EdError::InvalidCommandSuffix)?;
 
     // Step 4: Mark the line (GNU ed: mark_line_node(search_line_node(second_addr), n))
     buffer.mark_line_node(line_to_mark, mark_char)?;
 
-    Ok(())
+    print_current_line(buffer, pflags)
 }
 
 pub fn execute_goto_mark_command(buffer: &mut EdBuffer, command_args: &str) -> Result<(), EdError> {
@@ -1717,141 +1957,104 @@ pub fn execute_goto_mark_command(buffer: &mut EdBuffer, command_args: &str) -> R
 
     // Step 4: Print the line (same as GNU ed behavior for address navigation)
     if let Some(line) = buffer.get_line(marked_line) {
-        println!("{}", line);
+        outln!("{}", line);
     }
 
     Ok(())
 }
 
 pub fn execute_global_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction, match_flag: bool, interactive: bool) -> Result<(), EdError> {
-    // Global command implementation following GNU ed main_loop.c:609-620
-    // Supports both batch (g/v) and interactive (G/V) modes
+    // GNU ed main_loop.c:612 - a command list cannot contain another global
+    if isglobal() {
+        return Err(EdError::CannotNestGlobal);
+    }
+    // Commands in the list run with isglobal set (GNU ed exec_command( ibufpp, true ))
+    crate::session::with(|s| s.isglobal = true);
+    let result = run_global_command_list(buffer, command_args, addresses, match_flag, interactive);
+    crate::session::with(|s| {
+        s.isglobal = false;
+        s.global_commands.clear();
+    });
+    crate::global::clear_active_list();
+    result
+}
 
+/// run_global_command_list - the body of execute_global_command, run with isglobal set
+/// matches main_loop.c:755 exec_global: the command list runs through the
+/// normal dispatcher once per active line, stopping at the first error
+fn run_global_command_list(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction, match_flag: bool, interactive: bool) -> Result<(), EdError> {
     let (start, end) = if addresses.addr_count == 0 {
-        (1, buffer.len() as i32)  // Default to all lines for global command
+        // Default to all lines; an empty buffer has none (GNU ed set_addr_range)
+        if buffer.is_empty() {
+            return Err(EdError::InvalidAddress);
+        }
+        (1, buffer.len() as i32)
     } else {
         let (s, e) = get_address_range(buffer, addresses)?;
         (s as i32, e as i32)
     };
 
-    // Step 1: Build active list using proper regex matching (GNU ed regex.c:221)
+    // Build active list using proper regex matching (GNU ed regex.c:221)
     let mut command_args_mut = command_args;
-    if !crate::regex::build_active_list(&mut command_args_mut, start, end, match_flag, buffer) {
-        return Err(EdError::InvalidCommand);
-    }
+    crate::regex::build_active_list(&mut command_args_mut, start, end, match_flag, buffer)?;
 
-    // For interactive mode (G/V), validate print flags and use interactive prompting
+    // G and V take print flags only (GNU ed main_loop.c:618); g and v read
+    // the rest of their command list now, escaped newlines and all
+    let mut pflags = 0;
+    let mut cmd: Option<Vec<String>> = None;
     if interactive {
-        // GNU ed line 619: get_command_suffix validates print flags (p, l, n)
-        let pflags = command_args_mut.trim();
-        if !pflags.is_empty() && !matches!(pflags, "p" | "l" | "n" | "pn" | "pl" | "ln" | "pln") {
-            return Err(EdError::InvalidCommand);
-        }
-
-        // Clear undo stack before global execution (GNU ed main_loop.c:772)
-        buffer.clear_undo_stack();
-
-        // Interactive mode: print each line and prompt for command
-        // GNU ed exec_global lines 779-797
-        while let Some(line_addr) = crate::global::next_active_line() {
-            let _ = buffer.set_current_line(line_addr);
-
-            // Print the line (with pflags)
-            if let Some(line_content) = buffer.get_line(line_addr) {
-                println!("{}", line_content);
-            }
-
-            // Read command from stdin (GNU ed line 784)
-            let mut input = String::new();
-            match std::io::stdin().read_line(&mut input) {
-                Ok(0) => return Ok(()), // EOF - stop processing
-                Ok(_) => {
-                    let cmd = input.trim();
-                    // If just newline, continue to next line (GNU ed line 787)
-                    if cmd.is_empty() {
-                        continue;
-                    }
-                    // Execute the command on the current line (GNU ed exec_command)
-                    // Parse and execute the command
-                    match crate::execute_command(buffer, cmd) {
-                        Ok(_) => {}, // Command succeeded, continue
-                        Err(EdError::Quit) => return Err(EdError::Quit), // Quit command
-                        Err(_) => {} // Other errors - print ? and continue (GNU ed behavior)
-                    }
-                },
-                Err(_) => return Err(EdError::InvalidCommand),
-            }
-        }
-
-        return Ok(());
-    }
-
-    // Step 2: Parse remaining command after pattern (GNU ed exec_global logic)
-    let command_to_execute = if command_args_mut.trim().is_empty() ||
-                                command_args_mut.trim() == "\n" {
-        // Default command is print (GNU ed main_loop.c:764-765)
-        "p".to_string()
+        pflags = get_command_suffix(command_args_mut).ok_or(EdError::InvalidCommandSuffix)?;
+    } else if crate::traditional() && command_args_mut.trim().is_empty() {
+        cmd = Some(vec!["p".to_string()]);  // null cmd_list == 'p'
     } else {
-        command_args_mut.trim().to_string()
-    };
+        let list = crate::io::get_extended_line(command_args_mut, false)?;
+        cmd = Some(list.split('\n').map(str::to_string).collect());
+    }
 
-    // Step 3: Clear undo stack before global execution (GNU ed main_loop.c:772)
+    // Open the undo group the whole command list adds to (GNU ed main_loop.c:772)
     buffer.clear_undo_stack();
 
-    // Step 4: Execute commands on active lines (GNU ed exec_global main loop)
-    if command_to_execute == "d" {
-        // Special handling for delete: collect all indices and delete from highest to lowest
-        // to avoid index shifting issues
-        let mut indices_to_delete = Vec::new();
-        while let Some(line_addr) = crate::global::next_active_line() {
-            indices_to_delete.push(line_addr);
+    while let Some(line_addr) = crate::global::next_active_line() {
+        // A hangup ends the command list before the next line, so the
+        // main loop saves the buffer without waiting for the whole global
+        if crate::signal::hangup_pending() {
+            return Err(EdError::InvalidCommand);
         }
+        buffer.set_current_line(line_addr)?;
 
-        // Sort indices in descending order and delete from highest to lowest
-        indices_to_delete.sort_by(|a, b| b.cmp(a));
-        for line_addr in indices_to_delete {
-            buffer.delete_line(line_addr)?;
-        }
-    } else {
-        // For other commands, process normally (GNU ed exec_global:773-804)
-        while let Some(line_addr) = crate::global::next_active_line() {
-            let _ = buffer.set_current_line(line_addr);
-
-            // Execute the command following GNU ed exec_global logic
-            // Check if command starts with specific letter
-            let cmd_char = command_to_execute.chars().next().unwrap_or(' ');
-
-            match cmd_char {
-                'p' => {
-                    // Print command (GNU ed main_loop.c:765 - default behavior)
-                    if let Some(line_content) = buffer.get_line(line_addr) {
-                        println!("{}", line_content);
-                    }
-                },
-                's' => {
-                    // Substitute command in global context
-                    // Create a single-line address extraction for this line
-                    let line_addresses = AddressExtraction {
-                        first_addr: line_addr as i32,
-                        second_addr: line_addr as i32,
-                        addr_count: 2,
-                        remaining_command: String::new(),
-                    };
-
-                    // Extract the substitute command (everything after 's')
-                    let sub_command = &command_to_execute[1..];
-
-                    // Execute substitute on this single line
-                    // Ignore errors (GNU ed continues on error in global context)
-                    let _ = execute_substitute_command(buffer, sub_command, &line_addresses);
-                },
-                _ => {
-                    // For now, other commands in global context return error
-                    // GNU ed supports more commands but these are most common
-                    return Err(EdError::InvalidCommand);
+        if interactive {
+            // Print the line and get a command in global syntax (GNU ed main_loop.c:779-797)
+            crate::io::print_lines(buffer, line_addr, line_addr, pflags)?;
+            let mut input = String::new();
+            match io::BufRead::read_line(&mut crate::signal::Interruptible(io::stdin().lock()), &mut input) {
+                // EOF (or an incomplete last line, which GNU ed discards) aborts the
+                // global command; the current line stays at the last line visited
+                Ok(0) => return Err(EdError::UnexpectedEof),
+                Ok(_) if !input.ends_with('\n') => return Err(EdError::UnexpectedEof),
+                Ok(_) => {}
+                Err(_) => return Err(EdError::InvalidCommand),
+            }
+            let line = input.trim_end_matches('\n');
+            if line.is_empty() {
+                continue;
+            }
+            if line == "&" {
+                // & repeats the last command list
+                if cmd.is_none() {
+                    return Err(EdError::NoPreviousCommand);
                 }
+            } else {
+                let list = crate::io::get_extended_line(line, false)?;
+                cmd = Some(list.split('\n').map(str::to_string).collect());
             }
         }
+
+        // The commands of the list share it with the text of a, c and i
+        let list = cmd.clone().unwrap_or_default();
+        crate::session::with(|s| s.global_commands = list.into());
+        while let Some(command) = crate::session::with(|s| s.global_commands.pop_front()) {
+            crate::execute_command(buffer, command.trim_start())?;
+        }
     }
 
     Ok(())
@@ -1874,7 +2077,7 @@ pub fn execute_help_command() -> Result<(), EdError> {
     // Print last error message if it exists
     let error_msg = error_msg();
     if !error_msg.is_empty() {
-        println!("{}", error_msg);
+        outln!("{}", error_msg);
     }
     Ok(())
 }
@@ -1889,7 +2092,7 @@ pub fn execute_verbose_help_command() -> Result<(), EdError> {
     if verbose() {
         let error_msg = error_msg();
         if !error_msg.is_empty() {
-            println!("{}", error_msg);
+            outln!("{}", error_msg);
         }
     }
     Ok(())
@@ -1904,7 +2107,7 @@ pub fn execute_prompt_command() -> Result<(), EdError> {
     Ok(())
 }
 
-pub fn execute_scroll_command(buffer: &EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
+pub fn execute_scroll_command(buffer: &mut EdBuffer, command_args: &str, addresses: &AddressExtraction) -> Result<(), EdError> {
     // z command implementation following GNU ed main_loop.c:723-733
     // Syntax: [addr]z[n] - display n lines starting from addr (default: current+1, n=22)
 
@@ -1915,34 +2118,82 @@ pub fn execute_scroll_command(buffer: &EdBuffer, command_args: &str, addresses:
     } else {
         buffer.current_line() + 1  // Default to next line if no address
     };
+    if start_addr < 1 || start_addr > buffer.len() {
+        return Err(EdError::InvalidAddress);
+    }
 
     // Step 2: Parse window lines if provided (GNU ed lines 725-727)
-    let window_lines = if !command_args.trim().is_empty() {
-        // Parse the number from command_args
-        match command_args.trim().parse::<usize>() {
-            Ok(n) if n > 0 => n,
-            _ => 22, // Default window size (GNU ed default)
-        }
+    // Only a count starting with 1-9 is taken; anything else, including z0,
+    // is left for get_command_suffix and fails as "Invalid command suffix".
+    // A count is kept for later z commands (GNU ed set_window_lines)
+    let args = command_args.trim_end_matches('\n');
+    let digits = if args.starts_with(|c: char| ('1'..='9').contains(&c)) {
+        args.find(|c: char| !c.is_ascii_digit()).unwrap_or(args.len())
     } else {
-        22 // Default window size (GNU ed default)
+        0
     };
+    if digits > 0 {
+        let n = args[..digits].parse::<i32>().map_err(|_| EdError::NumberOutOfRange)?;
+        crate::signal::set_window_lines(n);
+    }
+    let window_lines = crate::signal::window_lines().max(1) as usize;
+    let pflags = get_command_suffix(&args[digits..]).ok_or(EdError::InvalidCommandSuffix)?;
 
     // Step 3: Calculate end address (GNU ed line 729-730)
-    // min(last_addr(), second_addr + window_lines - 1)
-    let last_addr = buffer.len();
-    let end_addr = std::cmp::min(last_addr, start_addr + window_lines - 1);
+    // GNU ed: min(last_addr(), second_addr + window_lines - 1)
+    // Enhancement: long lines wrap, so stop once the window's physical rows are filled
+    let columns = crate::signal::window_columns().max(1) as usize;
+    let end_addr = scroll_end_addr(buffer, start_addr, window_lines, columns);
 
     // Step 4: Print lines from start_addr to end_addr (GNU ed line 729-731)
-    // GNU ed uses print_lines() without numbering
+    // The suffix flags (n, l, p) select the print format as for 'p'
     for line_num in start_addr..=end_addr {
-        if let Some(line) = buffer.get_line(line_num) {
-            println!("{}", line);
+        if let Some(line) = buffer.get_sbuf_line(line_num) {
+            crate::io::print_line(line, line.len(), pflags, line_num);
         }
     }
 
+    // The last line shown becomes current, so the next z continues from it
+    buffer.set_current_line(end_addr)?;
     Ok(())
 }
 
+/// display_rows - physical terminal rows a line occupies when printed unlisted
+/// Tabs advance to the next multiple of 8 columns; an empty line still takes one row
+fn display_rows(line: &str, columns: usize) -> usize {
+    let mut col: usize = 0;
+    for ch in line.chars() {
+        if ch == '\t' {
+            col = (col / 8 + 1) * 8;
+        } else {
+            col += 1;
+        }
+    }
+    std::cmp::max(1, col.div_ceil(columns))
+}
+
+/// scroll_end_addr - last address that fits in a z window of `window_lines` rows
+/// Always includes start_addr so z makes progress even past a very long line
+fn scroll_end_addr(buffer: &EdBuffer, start_addr: usize, window_lines: usize, columns: usize) -> usize {
+    let last_addr = buffer.len();
+    let mut end_addr = start_addr;
+    let mut rows = 0;
+
+    for line_num in start_addr..=last_addr {
+        let line_rows = buffer.get_line(line_num).map_or(1, |line| display_rows(line, columns));
+        if rows > 0 && rows + line_rows > window_lines {
+            break;
+        }
+        rows += line_rows;
+        end_addr = line_num;
+        if rows >= window_lines {
+            break;
+        }
+    }
+
+    std::cmp::min(last_addr, end_addr)
+}
+
 pub fn execute_yank_command(buffer: &mut EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
     // y command implementation following GNU ed main_loop.c:719-722
     // Syntax: [addr1,addr2]y - yank (copy) lines to yank buffer
@@ -1957,6 +2208,7 @@ pub fn execute_yank_command(buffer: &mut EdBuffer, addresses: &AddressExtraction
 }
 
 pub fn execute_put_command(buffer: &mut EdBuffer, addresses: &AddressExtraction) -> Result<(), EdError> {
+    check_read_only()?;
     // x command implementation following GNU ed main_loop.c:713-718
     // Syntax: [addr]x - put (paste) yanked lines after addr
 
@@ -1976,10 +2228,104 @@ pub fn execute_put_command(buffer: &mut EdBuffer, addresses: &AddressExtraction)
     }
 
     // Clear undo stack (GNU ed line 716)
-    buffer.clear_undo_stack();
+    buffer.clear_undo_unless_global(isglobal());
 
     // Put the lines (GNU ed put_lines)
     buffer.put_lines(addr)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn write_and_quit_writes_buffer_and_returns_exit_code() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let mut buffer = EdBuffer::new();
+        buffer.append_line("hello".to_string());
+        buffer.append_line("world".to_string());
+
+        assert_eq!(write_and_quit(&mut buffer, Some(path)).unwrap(), 0);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "hello\nworld\n");
+        assert!(!buffer.modified());
+    }
+
+    #[test]
+    fn scroll_window_counts_wrapped_rows() {
+        let mut buffer = EdBuffer::new();
+        for _ in 0..10 {
+            // 100 columns wraps to 2 rows at 76 columns
+            buffer.append_line("x".repeat(100));
+        }
+
+        // A 6-row window holds three 2-row lines
+        assert_eq!(scroll_end_addr(&buffer, 1, 6, 76), 3);
+        // Short lines still map one line per row
+        assert_eq!(scroll_end_addr(&buffer, 1, 6, 200), 6);
+        // A single overlong line is still shown
+        assert_eq!(scroll_end_addr(&buffer, 1, 1, 76), 1);
+        // Never runs past the last line
+        assert_eq!(scroll_end_addr(&buffer, 9, 22, 76), 10);
+    }
+
+    #[test]
+    fn display_rows_expands_tabs() {
+        assert_eq!(display_rows("", 76), 1);
+        assert_eq!(display_rows("\tx", 8), 2);
+        assert_eq!(display_rows("abc\t", 8), 1);
+    }
+
+    #[test]
+    fn write_and_quit_without_filename_fails() {
+        let mut buffer = EdBuffer::new();
+        buffer.append_line("hello".to_string());
+
+        assert!(matches!(write_and_quit(&mut buffer, None), Err(EdError::InvalidFilename)));
+        assert!(buffer.modified());
+    }
+
+    #[test]
+    fn json_escape_quotes_special_characters() {
+        assert_eq!(json_escape("Invalid address"), "Invalid address");
+        assert_eq!(json_escape("Trailing backslash (\\)"), "Trailing backslash (\\\\)");
+        assert_eq!(json_escape("say \"hi\"\n"), "say \\\"hi\\\"\\n");
+    }
+
+    #[test]
+    fn read_command_line_rejects_overlong_line() {
+        let mut input = io::Cursor::new(b"1p\n0123456789\n2p\n".to_vec());
+
+        assert_eq!(read_command_line(&mut input, 8).unwrap().as_deref(), Some("1p\n"));
+        assert!(matches!(read_command_line(&mut input, 8), Err(EdError::LineTooLong)));
+        // The rest of the long line is discarded, not run as a command
+        assert_eq!(read_command_line(&mut input, 8).unwrap().as_deref(), Some("2p\n"));
+        assert_eq!(read_command_line(&mut input, 8).unwrap(), None);
+    }
+
+    #[test]
+    fn status_line_reports_count_current_and_modified() {
+        let mut buffer = EdBuffer::new();
+        assert_eq!(status_line(&buffer), "[0 lines, current 0, unmodified]");
+
+        buffer.append_line("one".to_string());
+        buffer.append_line("two".to_string());
+        buffer.set_current_line(1).unwrap();
+        assert_eq!(status_line(&buffer), "[2 lines, current 1, modified]");
+    }
+
+    #[test]
+    fn verbose_status_only_with_h_on_a_terminal() {
+        let mut buffer = EdBuffer::new();
+        buffer.append_line("one".to_string());
+        crate::session::with(|s| s.verbose = true);
+        assert_eq!(verbose_status(&buffer, true).as_deref(), Some("[1 lines, current 1, modified]"));
+        assert_eq!(verbose_status(&buffer, false), None);
+        crate::session::with(|s| s.verbose = false);
+        assert_eq!(verbose_status(&buffer, true), None);
+    }
+}
@@ -22,29 +22,35 @@
 
 use regex::Regex;
 use crate::error::EdError;
-use std::sync::Mutex;
+use crate::session;
 // Note: Many functions are placeholders until full buffer integration is complete
 // These imports will be updated as functions are properly implemented in their respective modules
 
 // Error message constants - matches regex.c:26-30
 static INV_I_SUF: &str = "Suffix 'I' not allowed on empty regexp";
-static INV_PAT_DEL: &str = "Invalid pattern delimiter";
-static MIS_PAT_DEL: &str = "Missing pattern delimiter";
+pub static INV_PAT_DEL: &str = "Invalid pattern delimiter";
+pub static MIS_PAT_DEL: &str = "Missing pattern delimiter";
 static NO_MATCH: &str = "No match";
 static NO_PREV_PAT: &str = "No previous pattern";
-static NO_PREV_SUBST: &str = "No previous substitution";
+pub static NO_PREV_SUBST: &str = "No previous substitution";
+static UNBAL_BRACKETS: &str = "Unbalanced brackets ([])";
+static TRAIL_BACKSLASH: &str = "Trailing backslash (\\)";
 
-// Static state converted to safe Rust - matches regex.c:31-36 functionality
-static LAST_REGEXP: Mutex<Option<Regex>> = Mutex::new(None);     // last regex found
-static SUBST_REGEXP: Mutex<Option<Regex>> = Mutex::new(None);    // regex of last substitution
+// regex.c:31-36 statics (last_regexp, subst_regexp, rbuf) live in session::Session
 
-// Replacement buffer state - safe Rust equivalents
-static RBUF: Mutex<Vec<u8>> = Mutex::new(Vec::new());            // replacement buffer
-static RLEN: Mutex<usize> = Mutex::new(0);                      // replacement length
+/// last_regexp - the regex of the last search or substitution, reused by an empty RE
+pub fn last_regexp() -> Option<Regex> {
+    session::with(|s| s.last_regexp.clone())
+}
+
+/// set_last_regexp - remember a regex compiled outside compile_regex (e.g. by 's')
+pub fn set_last_regexp(exp: &Regex) {
+    session::with(|s| s.last_regexp = Some(exp.clone()));
+}
 
 /// subst_regex - matches regex.c:39 (now memory safe)
 pub fn subst_regex() -> bool {
-    SUBST_REGEXP.lock().map_or(false, |guard| guard.is_some())
+    session::with(|s| s.subst_regexp.is_some())
 }
 
 /// translit_text - matches regex.c:43
@@ -110,7 +116,7 @@ fn parse_char_class(p: &str) -> Option<usize> {
 }
 
 /// extract_pattern - matches regex.c:84
-fn extract_pattern(ibufpp: &mut &str, delimiter: char) -> Option<String> {
+pub fn extract_pattern(ibufpp: &mut &str, delimiter: char) -> Result<String, &'static str> {
     let input = *ibufpp;
     let bytes = input.as_bytes();
     let mut i = 0;
@@ -118,16 +124,14 @@ fn extract_pattern(ibufpp: &mut &str, delimiter: char) -> Option<String> {
     while i < bytes.len() && bytes[i] != delimiter as u8 && !islf_or_nul(bytes[i]) {
         if bytes[i] == b'[' {
             if let Some(class_end) = parse_char_class(&input[i + 1..]) {
-                i += class_end + 2; // +1 for '[' and +1 for the relative position
+                i += class_end + 1; // now on the closing ']', as parse_char_class leaves nd
             } else {
-                // TODO: set_error_msg("Unbalanced brackets ([])");
-                return None;
+                return Err(UNBAL_BRACKETS);
             }
         } else if bytes[i] == b'\\' {
             i += 1;
             if i >= bytes.len() || islf_or_nul(bytes[i]) {
-                // TODO: set_error_msg("Trailing backslash (\\)");
-                return None;
+                return Err(TRAIL_BACKSLASH);
             }
         }
         i += 1;
@@ -139,11 +143,42 @@ fn extract_pattern(ibufpp: &mut &str, delimiter: char) -> Option<String> {
     // Handle binary mode newline conversion
     // TODO: Handle binary mode newline conversion  
     // if isbinary() { nul_to_newline conversion }
-    Some(pattern)
+    Ok(pattern)
+}
+
+/// regex_error_msg - the regerror() text GNU ed reports for a pattern
+/// that fails to compile (regex.c:127)
+pub fn regex_error_msg(err: &regex::Error) -> &'static str {
+    let text = match err {
+        regex::Error::Syntax(text) => text.as_str(),
+        regex::Error::CompiledTooBig(_) => return "Regular expression too big",
+        _ => return "Invalid regular expression",
+    };
+    if text.contains("unclosed character class") {
+        "Unmatched [, [^, [:, [., or [="
+    } else if text.contains("unclosed group") {
+        "Unmatched ( or \\("
+    } else if text.contains("unopened group") {
+        "Unmatched ) or \\)"
+    } else if text.contains("incomplete escape") {
+        "Trailing backslash"
+    } else if text.contains("repetition operator missing expression") {
+        "Invalid preceding regular expression"
+    } else if text.contains("counted repetition") || text.contains("decimal literal") {
+        "Invalid content of \\{\\}"
+    } else if text.contains("range") {
+        "Invalid range end"
+    } else if text.contains("backreferences") {
+        "Invalid back reference"
+    } else if text.contains("unrecognized") && text.contains("class") {
+        "Invalid character class name"
+    } else {
+        "Invalid regular expression"
+    }
 }
 
 /// compile_regex - matches regex.c:115
-fn compile_regex(pat: &str, ignore_case: bool) -> Option<Regex> {
+fn compile_regex(pat: &str, ignore_case: bool) -> Result<Regex, EdError> {
     // Build regex flags
     let mut builder = regex::RegexBuilder::new(pat);
     builder.case_insensitive(ignore_case);
@@ -152,38 +187,37 @@ fn compile_regex(pat: &str, ignore_case: bool) -> Option<Regex> {
     // TODO: Check extended_regexp() from main module
     // Extended regexes are default in Rust regex crate
     builder.multi_line(false); // GNU ed regexes are single-line by default
+    // No REG_NEWLINE either: '.' also matches the newlines that stand in
+    // for NULs while a binary line is matched
+    builder.dot_matches_new_line(true);
+    // GNU's word anchors \< and \> need no translation: the regex crate reads
+    // them as start and end of word. Its word characters are Unicode ones,
+    // so next to non-ASCII letters they can differ from a C-locale GNU ed
     
     match builder.build() {
         Ok(exp) => {
             // Free last_regexp if different from subst_regexp (now memory safe)
-            if let Ok(mut last_guard) = LAST_REGEXP.lock() {
-                // In Rust, RAII automatically handles cleanup - no manual regfree needed
-                *last_guard = Some(exp.clone());
-            }
-            Some(exp)
-        }
-        Err(err) => {
-            // TODO: set_error_msg(&format!("Regex error: {}", err));
-            None
+            // In Rust, RAII automatically handles cleanup - no manual regfree needed
+            session::with(|s| s.last_regexp = Some(exp.clone()));
+            Ok(exp)
         }
+        Err(err) => Err(EdError::InvalidPattern(regex_error_msg(&err))),
     }
 }
 
 /// get_compiled_regex - matches regex.c:142
-fn get_compiled_regex(ibufpp: &mut &str) -> Option<Regex> {
+fn get_compiled_regex(ibufpp: &mut &str) -> Result<Regex, EdError> {
     let input = *ibufpp;
     let bytes = input.as_bytes();
     
     if bytes.is_empty() {
-        // TODO: set_error_msg(INV_PAT_DEL);
-        return None;
+        return Err(EdError::InvalidPattern(INV_PAT_DEL));
     }
     
     let delimiter = bytes[0] as char;
     
     if delimiter == ' ' || islf_or_nul(delimiter as u8) {
-        // TODO: set_error_msg(INV_PAT_DEL);
-        return None;
+        return Err(EdError::InvalidPattern(INV_PAT_DEL));
     }
     
     *ibufpp = &input[1..]; // Move past delimiter
@@ -191,43 +225,31 @@ fn get_compiled_regex(ibufpp: &mut &str) -> Option<Regex> {
     // Check for empty RE
     if ibufpp.is_empty() || ibufpp.chars().next().unwrap() == delimiter || islf_or_nul(ibufpp.as_bytes()[0]) {
         // Safe access to last regex (converted from unsafe block)
-        match LAST_REGEXP.lock() {
-            Ok(guard) => {
-                if guard.is_none() {
-                    // TODO: set_error_msg(NO_PREV_PAT);
-                    return None;
-                }
-                
-                // Handle delimiter removal and 'I' suffix check
-                if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == delimiter {
-                    *ibufpp = &ibufpp[1..]; // Remove delimiter
-                    if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == 'I' {
-                        // TODO: set_error_msg(INV_I_SUF);
-                        return None;
-                    }
-                }
-                guard.clone()
+        let last = session::with(|s| s.last_regexp.clone()).ok_or(EdError::NoPreviousPattern)?;
+
+        // Handle delimiter removal and 'I' suffix check
+        if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == delimiter {
+            *ibufpp = &ibufpp[1..]; // Remove delimiter
+            if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == 'I' {
+                return Err(EdError::InvalidPattern(INV_I_SUF));
             }
-            Err(_) => None
         }
+        Ok(last)
     } else {
         // Extract pattern
-        if let Some(pat) = extract_pattern(ibufpp, delimiter) {
-            let mut ignore_case = false;
-            
-            // Check for delimiter and 'I' suffix
-            if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == delimiter {
-                *ibufpp = &ibufpp[1..]; // Remove delimiter
-                if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == 'I' {
-                    ignore_case = true;
-                    *ibufpp = &ibufpp[1..]; // Remove suffix
-                }
+        let pat = extract_pattern(ibufpp, delimiter).map_err(EdError::InvalidPattern)?;
+        let mut ignore_case = false;
+        
+        // Check for delimiter and 'I' suffix
+        if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == delimiter {
+            *ibufpp = &ibufpp[1..]; // Remove delimiter
+            if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == 'I' {
+                ignore_case = true;
+                *ibufpp = &ibufpp[1..]; // Remove suffix
             }
-            
-            compile_regex(&pat, ignore_case)
-        } else {
-            None
         }
+        
+        compile_regex(&pat, ignore_case)
     }
 }
 
@@ -253,14 +275,14 @@ pub fn get_pattern_for_s(ibufpp: &mut &str) -> Option<String> {
     // Check for empty RE
     if !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == delimiter {
         // Safe check for last regex (converted from unsafe)
-        if LAST_REGEXP.lock().map_or(true, |guard| guard.is_none()) {
+        if session::with(|s| s.last_regexp.is_none()) {
             // TODO: set_error_msg(NO_PREV_PAT);
             return None;
         }
         return Some(String::new()); // Empty pattern
     }
     
-    let pat = extract_pattern(ibufpp, delimiter)?;
+    let pat = extract_pattern(ibufpp, delimiter).ok()?;
     
     if ibufpp.is_empty() || ibufpp.chars().next().unwrap() != delimiter {
         // TODO: set_error_msg(MIS_PAT_DEL);
@@ -280,16 +302,14 @@ pub fn set_subst_regex(pat: Option<&str>, ignore_case: bool) -> bool {
         
         crate::signal::disable_interrupts();
         let exp = if pattern.is_empty() {
-            LAST_REGEXP.lock().ok().and_then(|guard| guard.clone())
+            last_regexp()
         } else {
-            compile_regex(pattern, ignore_case)
+            compile_regex(pattern, ignore_case).ok()
         };
         
         if let Some(regex) = exp {
             // Safe assignment (converted from unsafe)
-            if let Ok(mut guard) = SUBST_REGEXP.lock() {
-                *guard = Some(regex);
-            }
+            session::with(|s| s.subst_regexp = Some(regex));
             crate::signal::enable_interrupts();
             true
         } else {
@@ -304,25 +324,20 @@ pub fn set_subst_regex(pat: Option<&str>, ignore_case: bool) -> bool {
 /// replace_subst_re_by_search_re - matches regex.c:206
 pub fn replace_subst_re_by_search_re() -> bool {
     // Safe implementation (converted from unsafe)
-    if LAST_REGEXP.lock().map_or(true, |guard| guard.is_none()) {
+    if session::with(|s| s.last_regexp.is_none()) {
         // TODO: set_error_msg(NO_PREV_PAT);
         return false;
     }
     
     crate::signal::disable_interrupts();
-    if let (Ok(last_guard), Ok(mut subst_guard)) = (LAST_REGEXP.lock(), SUBST_REGEXP.lock()) {
-        *subst_guard = last_guard.clone();
-    }
+    session::with(|s| s.subst_regexp = s.last_regexp.clone());
     crate::signal::enable_interrupts();
     true
 }
 
 /// build_active_list - matches regex.c:221 (GNU ed main_loop.c:616)
-pub fn build_active_list(ibufpp: &mut &str, first_addr: i32, second_addr: i32, match_flag: bool, buffer: &crate::buffer::EdBuffer) -> bool {
-    let exp = match get_compiled_regex(ibufpp) {
-        Some(regex) => regex,
-        None => return false,
-    };
+pub fn build_active_list(ibufpp: &mut &str, first_addr: i32, second_addr: i32, match_flag: bool, buffer: &crate::buffer::EdBuffer) -> Result<(), EdError> {
+    let exp = get_compiled_regex(ibufpp)?;
 
     crate::global::clear_active_list();
 
@@ -340,35 +355,36 @@ pub fn build_active_list(ibufpp: &mut &str, first_addr: i32, second_addr: i32, m
             if matches == match_flag {
                 // Store 1-based address (addr) to match GNU ed convention
                 if !crate::global::set_active_line(addr as usize) {
-                    return false;
+                    return Err(EdError::InvalidCommand);
                 }
             }
         }
     }
 
-    true
+    Ok(())
 }
 
 /// next_matching_node_addr_with_buffer - matches regex.c:244
 /// Returns the address of the next line matching a regular expression in a given direction.
 /// Wraps around begin/end of editor buffer if necessary.
 pub fn next_matching_node_addr_with_buffer(ibufpp: &mut &str, buffer: &crate::buffer::EdBuffer) -> Result<usize, EdError> {
+    next_matching_node_addr_from(ibufpp, buffer, buffer.current_line())
+}
+
+/// next_matching_node_addr_from - search from a given current address, which
+/// a ';' in the same address list may already have moved (main_loop.c:290)
+pub fn next_matching_node_addr_from(ibufpp: &mut &str, buffer: &crate::buffer::EdBuffer, current_addr: usize) -> Result<usize, EdError> {
     // Determine search direction based on delimiter (GNU ed regex.c:246)
     let forward = !ibufpp.is_empty() && ibufpp.chars().next().unwrap() == '/';
 
     // Get compiled regex (GNU ed regex.c:247)
-    let exp = match get_compiled_regex(ibufpp) {
-        Some(regex) => regex,
-        None => return Err(EdError::InvalidCommand),
-    };
+    let exp = get_compiled_regex(ibufpp)?;
 
     // Store the compiled regex for future use (GNU ed regex.c:135)
-    if let Ok(mut last_guard) = LAST_REGEXP.lock() {
-        *last_guard = Some(exp.clone());
-    }
+    set_last_regexp(&exp);
 
     // Get current address (GNU ed regex.c:248)
-    let mut addr = buffer.current_line();
+    let mut addr = current_addr;
     let start_addr = addr;
 
     // Search with wrap-around (GNU ed regex.c:251-262)
@@ -380,16 +396,6 @@ pub fn next_matching_node_addr_with_buffer(ibufpp: &mut &str, buffer: &crate::bu
             if addr <= 1 { buffer.len() } else { addr - 1 }
         };
 
-        // Check if we wrapped around to start
-        if addr == start_addr {
-            break;
-        }
-
-        // Skip if addr is 0 (shouldn't happen with proper wrap-around)
-        if addr == 0 {
-            continue;
-        }
-
         // Get line content and test against regex (GNU ed regex.c:255-259)
         if let Some(line_content) = buffer.get_line(addr) { // addr is 1-based, get_line expects 1-based
             // Handle binary mode newline conversion if needed
@@ -400,10 +406,15 @@ pub fn next_matching_node_addr_with_buffer(ibufpp: &mut &str, buffer: &crate::bu
                 return Ok(addr);
             }
         }
+
+        // The current line is tested last, after wrapping all the way around (GNU ed regex.c:262)
+        if addr == start_addr || buffer.is_empty() {
+            break;
+        }
     }
 
     // No match found (GNU ed regex.c:263-264)
-    Err(EdError::PatternNotFound)
+    Err(EdError::NoMatch)
 }
 
 /// next_matching_node_addr - matches regex.c:244 (compatibility wrapper)
@@ -411,169 +422,96 @@ pub fn next_matching_node_addr_with_buffer(ibufpp: &mut &str, buffer: &crate::bu
 pub fn next_matching_node_addr(ibufpp: &mut &str) -> Result<usize, EdError> {
     // This is a placeholder that should not be called directly
     // The real implementation is next_matching_node_addr_with_buffer
-    Err(EdError::PatternNotFound)
+    Err(EdError::NoMatch)
 }
 
-/// extract_replacement - matches regex.c:270
-pub fn extract_replacement(ibufpp: &mut &str, isglobal: bool) -> bool {
-    let input = *ibufpp;
-    let bytes = input.as_bytes();
-    
-    if bytes.is_empty() {
-        // TODO: set_error_msg(MIS_PAT_DEL);
-        return false;
-    }
-    
-    let delimiter = bytes[0];
-    *ibufpp = &input[1..]; // Move past delimiter
-    
-    // Check for single '%' replacement
-    if !ibufpp.is_empty() && ibufpp.as_bytes()[0] == b'%' {
-        let next_chars = &ibufpp.as_bytes()[1..];
-        if !next_chars.is_empty() && 
-           (next_chars[0] == delimiter || 
-            (next_chars[0] == b'\n' && (!isglobal || next_chars.len() == 1))) {
-            *ibufpp = &ibufpp[1..]; // Move past '%'
-            // Safe check for replacement buffer (converted from unsafe)
-            if RBUF.lock().map_or(true, |guard| guard.is_empty()) {
-                // TODO: set_error_msg(NO_PREV_SUBST);
-                return false;
-            }
-            return true;
-        }
-    }
-    
-    // Extract replacement text
-    let mut replacement = Vec::new();
-    let mut i = 0;
-    let input_bytes = ibufpp.as_bytes();
-    
-    while i < input_bytes.len() && input_bytes[i] != delimiter {
-        if input_bytes[i] == b'\n' && (!isglobal || i + 1 >= input_bytes.len()) {
-            break;
-        }
-        
-        replacement.push(input_bytes[i]);
-        i += 1;
-        
-        // Handle escaped newlines (non-global mode)
-        if i >= 2 && replacement[replacement.len()-2] == b'\\' && 
-           replacement[replacement.len()-1] == b'\n' && !isglobal {
-            // Get next line from stdin
-            // This is a placeholder for stdin line reading
-            // TODO: Implement stdin line reading for multi-line replacements
-            break;
-        }
-    }
-    
-    crate::signal::disable_interrupts();
-    // Safe assignment (converted from unsafe)
-    if let (Ok(mut rbuf_guard), Ok(mut rlen_guard)) = (RBUF.lock(), RLEN.lock()) {
-        *rbuf_guard = replacement;
-        *rlen_guard = rbuf_guard.len();
-    }
-    crate::signal::enable_interrupts();
-    
-    *ibufpp = &ibufpp[i..]; // Update position
-    true
-}
+// extract_replacement - regex.c:270 - is done by main_loop's
+// extract_replacement, which reads the rest of a multi-line replacement
 
 /// replace_matched_text - matches regex.c:314
-fn replace_matched_text(txtbuf: &mut Vec<u8>, txt: &[u8], captures: &regex::Captures, re_nsub: usize) -> Result<(), EdError> {
-    // Safe iteration over replacement buffer (converted from unsafe)
-    if let Ok(rbuf_guard) = RBUF.lock() {
-        for &byte in rbuf_guard.iter() {
-            if byte == b'&' {
-                // Replace with full match
-                if let Some(full_match) = captures.get(0) {
-                    txtbuf.extend_from_slice(&txt[full_match.start()..full_match.end()]);
+/// & stands for the match and \1 to \9 for its groups; a backslash makes
+/// any other character, a newline included, stand for itself. In a binary
+/// buffer the copied text gets its NULs back
+pub fn replace_matched_text(txtbuf: &mut String, rbuf: &str, captures: &regex::Captures, binary: bool) {
+    let copy = |txtbuf: &mut String, txt: &str| {
+        if binary { txtbuf.push_str(&txt.replace('\n', "\0")) } else { txtbuf.push_str(txt) }
+    };
+    let mut chars = rbuf.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '&' => copy(txtbuf, &captures[0]),
+            '\\' => match chars.next() {
+                Some(digit @ '1'..='9') if (digit as usize - '0' as usize) < captures.len() => {
+                    let n = digit as usize - '0' as usize;
+                    copy(txtbuf, captures.get(n).map_or("", |m| m.as_str()));
                 }
-            } else if byte == b'\\' && !rbuf_guard.is_empty() {
-                // Handle backreferences \1-\9
-                // This is a simplified version - full implementation would handle all escapes
-                txtbuf.push(byte);
-            } else {
-                txtbuf.push(byte);
-            }
+                Some(escaped) => txtbuf.push(escaped),
+                None => {}
+            },
+            _ => txtbuf.push(ch),
         }
     }
-    Ok(())
 }
 
 /// line_replace - matches regex.c:351
-fn line_replace(txtbuf: &mut Vec<u8>, line_text: &str, snum: i32) -> Result<i32, EdError> {
-    // Safe access to substitution regex (converted from unsafe)
-    if let Ok(subst_guard) = SUBST_REGEXP.lock() {
-        if let Some(ref subst_regex) = *subst_guard {
-            let text = line_text.to_string();
-            let global = snum <= 0;
-            let mut changed = false;
-            let mut match_count = 0;
-            
-            // Handle binary mode
-            // TODO: Check isbinary() and implement nul_to_newline conversion
-            
-            // Find matches and replace
-            let result = if global {
-                subst_regex.replace_all(&text, |caps: &regex::Captures| {
-                    changed = true;
-                    // Placeholder for replacement logic
-                    "REPLACED".to_string()
-                })
-            } else {
-                subst_regex.replace(&text, |caps: &regex::Captures| {
-                    match_count += 1;
-                    if snum <= 0 || match_count == snum {
-                        changed = true;
-                        "REPLACED".to_string()
-                    } else {
-                        caps.get(0).unwrap().as_str().to_string()
-                    }
-                })
-            };
-            
-            if changed {
-                txtbuf.clear();
-                txtbuf.extend_from_slice(result.as_bytes());
-                txtbuf.push(b'\n');
-                Ok(txtbuf.len() as i32)
-            } else {
-                Ok(0) // No change
-            }
+/// Replaces match snum of a line, or every match if snum is 0, and returns
+/// the new text split at the newlines the replacement put in, or None if
+/// nothing matched. A binary line is matched with its NULs as newlines,
+/// as GNU ed does, and they are turned back into NULs afterwards.
+/// Like GNU ed, each search starts where the last match ended, so an empty
+/// match does not move on: a second one in a global substitute is an
+/// infinite loop ('s/^/#/g' is fine, 's/x*/-/g' on "a" is not)
+pub fn line_replace(regex: &Regex, line: &str, rbuf: &str, snum: i32, binary: bool) -> Result<Option<Vec<String>>, EdError> {
+    let text = if binary { line.replace('\0', "\n") } else { line.to_string() };
+    let restore = |txt: &str| if binary { txt.replace('\n', "\0") } else { txt.to_string() };
+    let global = snum <= 0;
+    let mut txtbuf = String::new();
+    let mut offset = 0;
+    let mut changed = false;
+    let mut matchno = 0;
+    let mut infloop = false;
+
+    // Later searches start where the last match ended with the text before
+    // it as context, so ^ no longer matches (GNU ed: REG_NOTBOL). Back at
+    // the start of the line, a newline put in front does the same; found
+    // carries how far such a search is shifted
+    let notbol = std::cell::OnceCell::new();
+    let mut found = regex.captures_at(&text, 0).map(|captures| (0, captures));
+    while let Some((shift, captures)) = found {
+        let Some(m) = captures.get(0) else { break };
+        let (start, end) = (m.start() - shift, m.end() - shift);
+        matchno += 1;
+        if global || snum == matchno {
+            changed = true;
+            txtbuf.push_str(&restore(&text[offset..start]));
+            replace_matched_text(&mut txtbuf, rbuf, &captures, binary);
         } else {
-            Ok(0) // No substitution regex available
+            txtbuf.push_str(&restore(&text[offset..end]));
         }
-    } else {
-        Ok(0) // Failed to lock mutex
-    }
-}
-
-/// search_and_replace - matches regex.c:406
-pub fn search_and_replace(first_addr: i32, second_addr: i32, snum: i32, isglobal: bool) -> bool {
-    let mut txtbuf = Vec::new();
-    let mut match_found = false;
-    
-    for addr in first_addr..=second_addr {
-        // Get line content from buffer
-        // This is a placeholder for the line replacement logic
-        // TODO: Implement full line replacement with undo support
-        
-        match line_replace(&mut txtbuf, "placeholder_line", snum) {
-            Ok(size) => {
-                if size > 0 {
-                    match_found = true;
-                    // Replace line in buffer
-                    // TODO: Implement line replacement in buffer with undo
-                }
+        let empty_here = end == offset;
+        offset = end;
+        if global && empty_here {
+            if infloop {
+                return Err(EdError::InfiniteSubstitutionLoop);
             }
-            Err(_) => return false,
+            infloop = true;
+        }
+        if offset >= text.len() || (changed && !global) {
+            break;
         }
+        found = if offset > 0 {
+            regex.captures_at(&text, offset).map(|captures| (0, captures))
+        } else {
+            let shifted: &String = notbol.get_or_init(|| format!("\n{}", text));
+            regex.captures_at(shifted, 1).map(|captures| (1, captures))
+        };
     }
-    
-    if !match_found && !isglobal {
-        // TODO: set_error_msg(NO_MATCH);
-        return false;
+    if !changed {
+        return Ok(None);
     }
-    
-    true
-}
\ No newline at end of file
+    txtbuf.push_str(&restore(&text[offset..]));
+    Ok(Some(txtbuf.split('\n').map(str::to_string).collect()))
+}
+
+// search_and_replace - regex.c:406 - is done by
+// main_loop::execute_substitute_command, which owns the buffer
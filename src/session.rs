@@ -0,0 +1,196 @@
+// rust-ed - Memory-safe replacement for GNU ed
+// Copyright (C) 2025 Brian Boynton, MD
+//
+// This file is part of rust-ed.
+//
+// rust-ed is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// rust-ed is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with rust-ed.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Editor session state (not in C original)
+//! GNU ed keeps this state in file-scope statics spread over main.c,
+//! main_loop.c, regex.c, global.c and io.c. rust-ed gathers it here so an
+//! embedder can run several isolated editors, one Session each.
+//!
+//! The accessors in those modules (last_regexp(), verbose(), scripted(), ...)
+//! read the session current on this thread. Every thread starts with its own
+//! default session; Session::enter makes another one current for a while.
+//! Signal and terminal state (signal.rs) stays process-wide, except the
+//! window size the z command sets.
+
+use regex::Regex;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+/// Session - everything an editor remembers between commands, except the buffer
+#[derive(Debug, Clone)]
+pub struct Session {
+    // Invocation options - matches main.c:44-52
+    pub extended_regexp: bool,
+    pub restricted: bool,
+    pub scripted: bool,
+    pub strip_cr: bool,
+    pub traditional: bool,
+    pub quiet: bool,
+    pub safe_names: bool,
+    pub read_only: bool,
+    pub trim_trailing_ws: bool,
+    pub safe_write: bool,
+    // Toggled by the 'P' and 'H' commands - matches main_loop.c:38-41
+    pub prompt_on: bool,
+    pub prompt_str: Cow<'static, str>,
+    pub verbose: bool,
+    pub(crate) error_msg: &'static str,
+    // Set while g/v/G/V run their command list (GNU ed: exec_command's isglobal)
+    pub(crate) isglobal: bool,
+    pub(crate) previous_shell_command: Option<String>,
+    // Regex state - matches regex.c:31-36
+    pub(crate) last_regexp: Option<Regex>,
+    pub(crate) subst_regexp: Option<Regex>,
+    pub(crate) rbuf: Vec<u8>,
+    // Suffixes of the last substitution, for 's' with no pattern (main_loop.c:421-423)
+    // subst_snum > 0 replaces that match, <= 0 replaces every match
+    pub(crate) subst_snum: i32,
+    pub(crate) subst_print: bool,
+    // Active list of the 'g' and 'v' commands - matches global.c:28-31
+    pub(crate) active_list: Vec<Option<isize>>,
+    pub(crate) active_idx: usize,
+    pub(crate) active_idxm: usize,
+    pub(crate) active_offset: isize,
+    // Rest of the command list running on the current line of a global
+    // command (GNU ed: *ibufpp in exec_global); a, c and i take text from it
+    pub(crate) global_commands: VecDeque<String>,
+    // io.c:26-27
+    pub(crate) linenum: i32,
+    pub(crate) unterminated_line: Option<usize>,
+    // signal.c:35 - LINES or argument of z command; -1 until first read
+    pub(crate) user_lines: i32,
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    /// new - a session with GNU ed's startup defaults
+    pub const fn new() -> Self {
+        Session {
+            extended_regexp: false,
+            restricted: false,
+            scripted: false,
+            strip_cr: false,
+            traditional: false,
+            quiet: false,
+            safe_names: true,
+            read_only: false,
+            trim_trailing_ws: false,
+            safe_write: false,
+            prompt_on: false,
+            prompt_str: Cow::Borrowed("*"),
+            verbose: false,
+            error_msg: "",
+            isglobal: false,
+            previous_shell_command: None,
+            last_regexp: None,
+            subst_regexp: None,
+            rbuf: Vec::new(),
+            subst_snum: 1,
+            subst_print: false,
+            active_list: Vec::new(),
+            active_idx: 0,
+            active_idxm: 0,
+            active_offset: 0,
+            global_commands: VecDeque::new(),
+            linenum: 0,
+            unterminated_line: None,
+            user_lines: -1,
+        }
+    }
+
+    /// enter - run f with this session current on this thread
+    /// The previously current session is restored afterwards, also on panic.
+    pub fn enter<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        struct Restore<'a>(&'a mut Session);
+
+        impl Drop for Restore<'_> {
+            fn drop(&mut self) {
+                CURRENT.with(|current| std::mem::swap(&mut *current.borrow_mut(), self.0));
+            }
+        }
+
+        CURRENT.with(|current| std::mem::swap(&mut *current.borrow_mut(), self));
+        let _restore = Restore(self);
+        f()
+    }
+}
+
+thread_local! {
+    static CURRENT: RefCell<Session> = const { RefCell::new(Session::new()) };
+}
+
+/// with - access the session current on this thread
+/// f must not call back into with(); keep it to plain field access.
+pub fn with<R>(f: impl FnOnce(&mut Session) -> R) -> R {
+    CURRENT.with(|current| f(&mut current.borrow_mut()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_keep_separate_regex_histories() {
+        let mut buffer = crate::buffer::EdBuffer::new();
+        buffer.append_line("alpha".to_string());
+        buffer.append_line("beta".to_string());
+        let mut first = Session::new();
+        let mut second = Session::new();
+
+        first.enter(|| {
+            let mut search = "/alpha/";
+            assert!(crate::regex::next_matching_node_addr_with_buffer(&mut search, &buffer).is_ok());
+        });
+        second.enter(|| {
+            assert!(crate::regex::last_regexp().is_none());
+            let mut search = "/beta/";
+            assert!(crate::regex::next_matching_node_addr_with_buffer(&mut search, &buffer).is_ok());
+        });
+
+        assert_eq!(first.last_regexp.as_ref().map(Regex::as_str), Some("alpha"));
+        assert_eq!(second.last_regexp.as_ref().map(Regex::as_str), Some("beta"));
+        assert!(crate::regex::last_regexp().is_none());
+    }
+
+    #[test]
+    fn enter_restores_the_outer_session() {
+        with(|session| session.verbose = false);
+        let mut inner = Session::new();
+        inner.enter(crate::main_loop::set_verbose);
+        assert!(inner.verbose);
+        assert!(!crate::main_loop::verbose());
+    }
+
+    #[test]
+    fn sessions_keep_separate_window_sizes() {
+        let mut first = Session::new();
+        let mut second = Session::new();
+
+        first.enter(|| crate::signal::set_window_lines(5));
+        second.enter(|| crate::signal::set_window_lines(40));
+
+        assert_eq!(first.enter(crate::signal::window_lines), 5);
+        assert_eq!(second.enter(crate::signal::window_lines), 40);
+    }
+}
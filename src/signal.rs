@@ -21,10 +21,12 @@
 /// C source: signal.c (202 lines, 5,713 bytes) - IMMUTABLE REFERENCE
 
 use std::env;
+use std::io::{self, BufRead, Read};
 use std::process;
 use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
 // Note: Signal handling using nix crate for POSIX compatibility
-// TODO: Add libc dependency or use nix crate for proper signal handling
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use crate::buffer::EdBuffer;
 use crate::error::EdError;
 
 // Jump buffer for longjmp equivalent - matches signal.c:32
@@ -41,7 +43,6 @@ static JMP_STATE: JmpState = JmpState {
 
 // Static state matching signal.c:33-39
 static MUTEX: AtomicI32 = AtomicI32::new(0);           // if > 0, signals stay pending
-static USER_LINES: AtomicI32 = AtomicI32::new(-1);     // LINES or argument of z command
 static WINDOW_LINES_: AtomicI32 = AtomicI32::new(22);  // scroll lines set by sigwinch_handler  
 static WINDOW_COLUMNS_: AtomicI32 = AtomicI32::new(76);
 static SIGHUP_PENDING: AtomicBool = AtomicBool::new(false);
@@ -55,38 +56,52 @@ pub fn home_directory() -> Option<String> {
     env::var("HOME").ok().filter(|s| !s.is_empty())
 }
 
-/// sighup_handler - matches signal.c:62 (safe Rust implementation)
-fn sighup_handler() {
-    // Safe Rust signal handler - no FFI required
-    
-    if MUTEX.load(Ordering::SeqCst) > 0 {
-        SIGHUP_PENDING.store(true, Ordering::SeqCst);
-        return;
-    }
-    
+/// sighup_handler - matches signal.c:62
+/// Runs from the main loop once a hangup is pending, since the signal
+/// handler itself cannot reach the buffer; saves a modified buffer to
+/// ed.hup, or failing that $HOME/ed.hup, and exits
+pub fn sighup_handler(buffer: &EdBuffer) -> ! {
     SIGHUP_PENDING.store(false, Ordering::SeqCst);
-    
-    // Try to write ed.hup file 
-    // TODO: Implement buffer access for last_addr(), modified(), write_file()
-    // if last_addr() <= 0 || !modified() || write_file("ed.hup", "w", 1, last_addr()) >= 0 {
-    //     process::exit(0);
-    // }
-    
-    // Try home directory backup
-    if let Some(hd) = home_directory() {
-        let need_slash = !hd.ends_with('/');
-        let hup_path = if need_slash {
-            format!("{}/ed.hup", hd)
-        } else {
-            format!("{}ed.hup", hd)
-        };
-        
-        // TODO: Check path_max and implement write_file
-        // if path length OK and write_file succeeds, exit(0)
-        process::exit(0); // Simplified for now
+    let hb = "ed.hup";
+    let last_addr = buffer.last_addr();
+    if last_addr == 0 || !buffer.modified()
+        || crate::io::write_file(hb, "w", 1, last_addr, buffer).is_ok() {
+        process::exit(0);
     }
-    
-    process::exit(1);
+    let Some(hd) = home_directory() else { process::exit(1) };
+    let need_slash = !hd.ends_with('/');
+    let hup = format!("{}{}{}", hd, if need_slash { "/" } else { "" }, hb);
+    if hup.len() >= EdBuffer::path_max(None) as usize {
+        process::exit(1);
+    }
+    if crate::io::write_file(&hup, "w", 1, last_addr, buffer).is_ok() {
+        process::exit(0);
+    }
+    process::exit(1); // hup file write failed
+}
+
+/// hangup_pending - a SIGHUP or SIGTERM arrived and the buffer must be saved
+/// (not in C original: GNU ed saves from inside the signal handler)
+pub fn hangup_pending() -> bool {
+    SIGHUP_PENDING.load(Ordering::SeqCst)
+}
+
+/// sighup_signal - the handler installed for SIGHUP and SIGTERM; it only
+/// flags the hangup, which is async-signal-safe (not in C original)
+extern "C" fn sighup_signal(_signum: nix::libc::c_int) {
+    SIGHUP_PENDING.store(true, Ordering::SeqCst);
+}
+
+/// sigint_signal - the handler installed for SIGINT; sigint_handler only
+/// touches atomics, so it is safe to run here (not in C original)
+extern "C" fn sigint_signal(_signum: nix::libc::c_int) {
+    sigint_handler();
+}
+
+/// interrupt_pending - a SIGINT is waiting for the main loop, which
+/// check_interrupt_jump clears (not in C original)
+pub fn interrupt_pending() -> bool {
+    JMP_STATE.should_jump.load(Ordering::SeqCst)
 }
 
 /// sigint_handler - matches signal.c:85  
@@ -107,30 +122,63 @@ fn sigint_handler() {
 }
 
 /// sigwinch_handler - matches signal.c:100
+/// Rust version runs on demand from window_lines/window_columns rather than
+/// from a SIGWINCH handler, so a resize is seen by the next z without an
+/// unsafe signal handler
 fn sigwinch_handler() {
-    // Safe Rust signal handler - no FFI required
-    
-    // Get terminal window size
-    // TODO: Implement TIOCGWINSZ ioctl equivalent in Rust
-    // For now, use default values
-    
-    // In the C version:
-    // if ioctl success and sanity checks pass:
-    //   if ws.ws_row > 2 && ws.ws_row < 600: window_lines_ = ws.ws_row - 2
-    //   if ws.ws_col > 8 && ws.ws_col < 1800: window_columns_ = ws.ws_col - 4
-    
-    // Placeholder implementation
-    // WINDOW_LINES_.store(22, Ordering::SeqCst);   // Default
-    // WINDOW_COLUMNS_.store(76, Ordering::SeqCst); // Default
+    if let Ok(ws) = rustix::termios::tcgetwinsize(std::io::stdin()) {
+        set_window_size(ws.ws_row, ws.ws_col);
+    }
+}
+
+/// set_window_size - the sanity checks of signal.c:107-109
+fn set_window_size(rows: u16, cols: u16) {
+    if rows > 2 && rows < 600 {
+        WINDOW_LINES_.store(i32::from(rows) - 2, Ordering::SeqCst);
+    }
+    if cols > 8 && cols < 1800 {
+        WINDOW_COLUMNS_.store(i32::from(cols) - 4, Ordering::SeqCst);
+    }
 }
 
 /// set_signal - matches signal.c:116
-fn set_signal(signum: i32) -> i32 {
-    // Safe Rust signal handling - no unsafe FFI required
-    // TODO: Implement with signal-hook crate for complete safety  
-    // For now, return success placeholder
-    let _ = signum;
-    0 // Success
+/// Without SA_RESTART, unlike GNU ed: a read of stdin blocked when the
+/// signal arrives fails with EINTR, so the main loop gets to act on it
+fn set_signal(signal: Signal, handler: SigHandler) -> nix::Result<SigAction> {
+    let action = SigAction::new(handler, SaFlags::empty(), SigSet::empty());
+    // SAFETY: the handlers installed here only store to atomics
+    unsafe { sigaction(signal, &action) }
+}
+
+/// Interruptible - a reader of stdin that stops retrying a read cut short
+/// by a signal once that signal is pending (not in C original)
+pub struct Interruptible<R>(pub R);
+
+impl<R: BufRead> Read for Interruptible<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        interrupted(self.0.read(buf))
+    }
+}
+
+impl<R: BufRead> BufRead for Interruptible<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        interrupted(self.0.fill_buf())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.0.consume(amt)
+    }
+}
+
+/// interrupted - turn EINTR into an error the std readers do not retry
+/// when a hangup or an interrupt is pending
+fn interrupted<T>(result: io::Result<T>) -> io::Result<T> {
+    match result {
+        Err(e) if e.kind() == io::ErrorKind::Interrupted && (hangup_pending() || interrupt_pending()) => {
+            Err(io::Error::other("interrupted"))
+        }
+        result => result,
+    }
 }
 
 /// enable_interrupts - matches signal.c:131
@@ -139,9 +187,7 @@ pub fn enable_interrupts() {
     if current_mutex <= 1 {
         MUTEX.store(0, Ordering::SeqCst);
         
-        if SIGHUP_PENDING.load(Ordering::SeqCst) {
-            sighup_handler(); // TODO: Use proper SIGHUP constant
-        }
+        // A pending hangup is acted on by the main loop, which owns the buffer
         if SIGINT_PENDING.load(Ordering::SeqCst) {
             sigint_handler(); // TODO: Use proper SIGINT constant
         }
@@ -155,27 +201,29 @@ pub fn disable_interrupts() {
 
 /// set_signals - matches signal.c:145
 pub fn set_signals() {
-    // Set up signal handlers
-    // TODO: Implement signal setup with proper signal constants
-    // set_signal(SIGHUP, sighup_handler);
-    // set_signal(SIGPIPE, SIG_IGN);
+    // SIGTERM is saved like a hangup (not in C original, where it kills ed)
+    let _ = set_signal(Signal::SIGHUP, SigHandler::Handler(sighup_signal));
+    let _ = set_signal(Signal::SIGTERM, SigHandler::Handler(sighup_signal));
+    // SIGPIPE needs no handler: the Rust runtime already ignores it, and
+    // out!/outln! drop writes to a closed stdout (set_signal(SIGPIPE, SIG_IGN))
     // set_signal(SIGQUIT, SIG_IGN); 
-    // set_signal(SIGINT, sigint_handler);
+    let _ = set_signal(Signal::SIGINT, SigHandler::Handler(sigint_signal));
 }
 
 /// set_window_lines - matches signal.c:158
 pub fn set_window_lines(lines: i32) {
-    USER_LINES.store(lines, Ordering::SeqCst);
+    crate::session::with(|s| s.user_lines = lines);
 }
 
 /// window_columns - matches signal.c:159
 pub fn window_columns() -> i32 {
+    sigwinch_handler();
     WINDOW_COLUMNS_.load(Ordering::SeqCst)
 }
 
 /// window_lines - matches signal.c:162
 pub fn window_lines() -> i32 {
-    let mut user_lines = USER_LINES.load(Ordering::SeqCst);
+    let mut user_lines = crate::session::with(|s| s.user_lines);
     
     if user_lines < 0 {
         // Set initial size from environment
@@ -183,20 +231,21 @@ pub fn window_lines() -> i32 {
             if let Ok(n) = lines_str.parse::<i32>() {
                 if n > 0 && n <= i32::MAX {
                     user_lines = n;
-                    USER_LINES.store(user_lines, Ordering::SeqCst);
+                    crate::session::with(|s| s.user_lines = user_lines);
                 }
             }
         }
         
         if user_lines < 0 {
             user_lines = 0; // LINES not found or invalid
-            USER_LINES.store(0, Ordering::SeqCst);
+            crate::session::with(|s| s.user_lines = 0);
         }
     }
     
     if user_lines > 0 {
         user_lines
     } else {
+        sigwinch_handler();
         WINDOW_LINES_.load(Ordering::SeqCst)
     }
 }
@@ -254,4 +303,25 @@ pub fn check_interrupt_jump() -> Option<i32> {
     } else {
         None
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_size_follows_resize_within_sane_bounds() {
+        set_window_size(50, 120);
+        assert_eq!(WINDOW_LINES_.load(Ordering::SeqCst), 48);
+        assert_eq!(WINDOW_COLUMNS_.load(Ordering::SeqCst), 116);
+
+        // Out-of-range sizes are ignored, as in GNU ed
+        set_window_size(2, 8);
+        assert_eq!(WINDOW_LINES_.load(Ordering::SeqCst), 48);
+        assert_eq!(WINDOW_COLUMNS_.load(Ordering::SeqCst), 116);
+
+        set_window_size(30, 80);
+        assert_eq!(WINDOW_LINES_.load(Ordering::SeqCst), 28);
+        assert_eq!(WINDOW_COLUMNS_.load(Ordering::SeqCst), 76);
+    }
+}
@@ -25,6 +25,14 @@ pub struct TestCase {
     /// Used for testing "ed newfilename" where newfilename doesn't exist
     /// Default: false (file exists with input_text content)
     pub file_should_not_exist: bool,
+
+    /// If true, ed is launched with a directory in place of the test file
+    /// Default: false
+    pub file_is_directory: bool,
+
+    /// Command-line options passed to ed before the filename (e.g., ["-s"])
+    /// Default: none
+    pub options: Vec<String>,
 }
 
 impl TestCase {
@@ -36,6 +44,17 @@ impl TestCase {
             commands: commands.to_string(),
             input_text: input_text.to_string(),
             file_should_not_exist: false,
+            file_is_directory: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Create a test case with existing file, launching ed with the given options
+    /// Used for option-dependent behavior such as "ed -s file"
+    pub fn new_with_options(name: &str, category: &str, options: &[&str], commands: &str, input_text: &str) -> Self {
+        Self {
+            options: options.iter().map(|o| o.to_string()).collect(),
+            ..Self::new(name, category, commands, input_text)
         }
     }
 
@@ -48,6 +67,17 @@ impl TestCase {
             commands: commands.to_string(),
             input_text: String::new(),
             file_should_not_exist: true,
+            file_is_directory: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Create a test case where ed is launched with a directory as its file
+    /// Used for "ed somedir", which GNU ed reports and then starts empty
+    pub fn new_directory_operand(name: &str, category: &str, commands: &str) -> Self {
+        Self {
+            file_is_directory: true,
+            ..Self::new(name, category, commands, "")
         }
     }
 }
@@ -79,5 +79,92 @@ pub fn get_test_suite() -> TestSuite {
         "Line 1\nLine 2\nLine 3\n"
     ));
 
+    // Leading comma with a following address: ",3" is 1,3
+    suite.add_test(TestCase::new(
+        "address_comma_second_only",
+        "addressing",
+        ",3p\nq\n",
+        "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n"
+    ));
+
+    // Trailing comma: "2," is 2,2 in GNU ed, not 2,$
+    suite.add_test(TestCase::new(
+        "address_comma_first_only",
+        "addressing",
+        "2,p\nq\n",
+        "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n"
+    ));
+
+    // Blanks between addresses are skipped, and a number after an address is an offset
+    suite.add_test(TestCase::new(
+        "address_blanks_and_offsets",
+        "addressing",
+        "1, 3p\n2 1p\nq\n",
+        "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n"
+    ));
+
+    // Semicolon makes the first address current for the second
+    suite.add_test(TestCase::new(
+        "address_semicolon_relative",
+        "addressing",
+        "2;+2p\nq\n",
+        "Line 1\nLine 2\nLine 3\nLine 4\nLine 5\n"
+    ));
+
+    // A bare search prints the match and makes it the current line
+    suite.add_test(TestCase::new(
+        "bare_search_sets_current_line",
+        "addressing",
+        "1\n/bar/\n.=\n?foo?\n.=\nq\n",
+        "foo\nbar\nbaz\n"
+    ));
+
+    // A mark as the first address of a range
+    suite.add_test(TestCase::new(
+        "mark_as_range_start",
+        "addressing",
+        "2ka\n'a,$p\nq\n",
+        "a\nb\nc\nd\n"
+    ));
+
+    // A mark as the second address of a range
+    suite.add_test(TestCase::new(
+        "mark_as_range_end",
+        "addressing",
+        "3ka\n1,'ap\nq\n",
+        "a\nb\nc\nd\n"
+    ));
+
+    // Offsets from a mark and a range between two marks
+    suite.add_test(TestCase::new(
+        "mark_offsets_and_two_marks",
+        "addressing",
+        "2ka\n'a+1,'a+2p\n3kb\n'a,'bp\nq\n",
+        "a\nb\nc\nd\n"
+    ));
+
+    // A bare ';' means .,$ just as a bare ',' means 1,$
+    suite.add_test(TestCase::new(
+        "semicolon_alone_current_to_last",
+        "addressing",
+        "2\n;p\n.=\nq\n",
+        "line 1\nline 2\nline 3\nline 4\n"
+    ));
+
+    // ';' makes the left address current before the right one is read
+    suite.add_test(TestCase::new(
+        "semicolon_separator_sets_current",
+        "addressing",
+        "2\n3;+1p\n.=\nq\n",
+        "line 1\nline 2\nline 3\nline 4\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "semicolon_alone_no_command",
+        "addressing",
+        "2\n;\n.=\nq\n",
+        "line 1\nline 2\nline 3\nline 4\n"
+    ));
+
     suite
 }
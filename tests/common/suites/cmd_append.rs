@@ -45,5 +45,30 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // Only a line that is exactly "." ends input; GNU ed has no escape
+    // for it, but lines that merely start with "." are text
+    suite.add_test(TestCase::new(
+        "append_dot_prefixed_lines",
+        "append",
+        "a\n. \n..\n.x\n.\n,l\n.=\nQ\n",
+        "line 1\n"
+    ));
+
+    // The last appended line becomes current
+    suite.add_test(TestCase::new(
+        "append_sets_current_to_last_line",
+        "append",
+        "1a\nA\nB\nC\n.\n.=\ni\nX\n.\n,p\nQ\n",
+        "line 1\nline 2\n"
+    ));
+
+    // 0a adds text before the first line
+    suite.add_test(TestCase::new(
+        "append_after_address_zero",
+        "append",
+        "0a\nfirst\n.\n.=\n,p\nQ\n",
+        "line 1\n"
+    ));
+
     suite
 }
@@ -45,5 +45,13 @@ pub fn get_test_suite() -> TestSuite {
         "old 1\nold 2\nold 3\n"
     ));
 
+    // A p suffix prints the last line of the new text
+    suite.add_test(TestCase::new(
+        "change_print_suffix",
+        "change",
+        "1cp\nnew 1\nnew 1b\n.\n,p\nQ\n",
+        "old 1\nold 2\nold 3\n"
+    ));
+
     suite
 }
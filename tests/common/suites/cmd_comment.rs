@@ -0,0 +1,49 @@
+/// Test suite for the comment command (#)
+/// GNU ed reference: main_loop.c case '#' (line 743)
+///
+/// The comment command skips the rest of the line. Addresses may precede
+/// it but do not change the current line.
+/// Syntax: [addr1,addr2]# text
+
+use crate::common::{TestCase, TestSuite};
+
+pub fn get_test_suite() -> TestSuite {
+    let mut suite = TestSuite::new(
+        "cmd_comment",
+        "Comment command (#)"
+    );
+
+    // A comment line in a script does nothing
+    suite.add_test(TestCase::new(
+        "comment_in_script",
+        "comment",
+        "# this is a comment\n1d\n# another one\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // A bare # is a comment too
+    suite.add_test(TestCase::new(
+        "comment_empty",
+        "comment",
+        "#\n.=\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // Addresses are accepted and leave the current line alone
+    suite.add_test(TestCase::new(
+        "comment_with_address_range",
+        "comment",
+        "1,2# skipped\n.=\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Inside a global command the comment still runs on every matching line
+    suite.add_test(TestCase::new(
+        "comment_in_global",
+        "comment",
+        "g/line/# nothing\n.=\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite
+}
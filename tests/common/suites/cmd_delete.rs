@@ -69,5 +69,38 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // After d the line that took the deleted line's place is current
+    suite.add_test(TestCase::new(
+        "delete_middle_then_print",
+        "delete",
+        "2d\np\n.=\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Deleting the last line makes the new last line current
+    suite.add_test(TestCase::new(
+        "delete_last_then_print",
+        "delete",
+        "$d\np\n.=\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Deleting the only line leaves no current line to print
+    suite.add_test(TestCase::new(
+        "delete_only_line_then_print",
+        "delete",
+        "1d\np\n.=\nQ\n",
+        "only line\n"
+    ));
+
+    // The unterminated last line of a binary file stays last, and stays
+    // unterminated, when lines before it are deleted
+    suite.add_test(TestCase::new(
+        "delete_before_unterminated_binary_last_line",
+        "delete",
+        "1d\nw\nq\n",
+        "a\0b\nc\nd"
+    ));
+
     suite
 }
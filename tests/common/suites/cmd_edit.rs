@@ -38,5 +38,45 @@ pub fn get_test_suite() -> TestSuite {
         "old content\n"
     ));
 
+    // E without filename reverts to on-disk content, discarding changes (no warning)
+    suite.add_test(TestCase::new(
+        "edit_force_revert_no_filename",
+        "edit",
+        "a\nunsaved line\n.\nE\n,p\nq\n",
+        "original line 1\noriginal line 2\n"
+    ));
+
+    // Undo after a revert must not undo the re-read (GNU ed reset_undo_state)
+    suite.add_test(TestCase::new(
+        "edit_force_revert_then_undo",
+        "edit",
+        "1d\nE\nu\n,p\nq\n",
+        "original line 1\noriginal line 2\n"
+    ));
+
+    // Editing an empty file leaves no current line
+    suite.add_test(TestCase::new(
+        "edit_empty_file_current_zero",
+        "edit",
+        "2\n1,$d\nw /tmp/test_edit_empty.txt\nE /tmp/test_edit_empty.txt\n=\n.=\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // e replaces every line, so marks go with them (GNU ed delete_lines
+    // unmarks each line); the prompt and verbose settings stay
+    suite.add_test(TestCase::new(
+        "edit_clears_marks",
+        "edit",
+        "2ka\ne !echo other\n'ap\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "edit_keeps_prompt_and_verbose",
+        "edit",
+        "2ka\nH\nP\ne !echo other\n'ap\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
     suite
 }
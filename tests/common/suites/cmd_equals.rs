@@ -44,5 +44,52 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // A range prints its second address
+    suite.add_test(TestCase::new(
+        "line_number_range",
+        "equals",
+        "1,3=\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // A trailing comma repeats the address, so 2,= is 2,2
+    suite.add_test(TestCase::new(
+        "line_number_trailing_comma",
+        "equals",
+        "2,=\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // A search address prints the matching line number
+    suite.add_test(TestCase::new(
+        "line_number_search_address",
+        "equals",
+        "/line 2/=\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // An empty buffer has last address 0, so = prints 0 rather than failing
+    suite.add_test(TestCase::new(
+        "equals_empty_buffer",
+        "equals",
+        "=\n0=\n.=\nq\n",
+        ""
+    ));
+
+    // 0= prints 0 in a non-empty buffer too
+    suite.add_test(TestCase::new(
+        "equals_address_zero",
+        "equals",
+        "0=\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // A buffer that never had a file is empty as well
+    suite.add_test(TestCase::new_nonexistent_file(
+        "equals_new_file",
+        "equals",
+        "=\nq\n"
+    ));
+
     suite
 }
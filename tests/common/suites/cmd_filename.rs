@@ -36,5 +36,31 @@ pub fn get_test_suite() -> TestSuite {
         "content\n"
     ));
 
+    // A tab in the filename is shown as \t
+    suite.add_test(TestCase::new_with_options(
+        "filename_tab_escaped",
+        "filename",
+        &["--unsafe-names"],
+        "f a\tb\nf\nq\n",
+        "content\n"
+    ));
+
+    // Control characters without a C escape are shown in octal
+    suite.add_test(TestCase::new_with_options(
+        "filename_control_char_octal",
+        "filename",
+        &["--unsafe-names"],
+        "f a\x01b\x1bc\nf\nq\n",
+        "content\n"
+    ));
+
+    // A backslash in the filename is doubled
+    suite.add_test(TestCase::new(
+        "filename_backslash_escaped",
+        "filename",
+        "f a\\b\nf\nq\n",
+        "content\n"
+    ));
+
     suite
 }
@@ -54,5 +54,211 @@ pub fn get_test_suite() -> TestSuite {
         "print this\nskip this\nprint that\n"
     ));
 
+    // Interactive global cut short by EOF: remaining matches are dropped with an error
+    suite.add_test(TestCase::new(
+        "interactive_global_eof",
+        "interactive_global",
+        "G/line/\n\ns/line/LINE/\n",
+        "line 1\nline 2\nline 3\nother\n"
+    ));
+
+    // An incomplete last line at the G prompt is discarded like EOF
+    suite.add_test(TestCase::new(
+        "interactive_global_eof_partial_command",
+        "interactive_global",
+        "G/line/\ns/line/LINE/",
+        "line 1\nline 2\n"
+    ));
+
+    // No matching lines - the command list never runs and nothing changes
+    suite.add_test(TestCase::new(
+        "global_no_match_is_noop",
+        "global",
+        "2\ng/zzz/d\n.=\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Every line deleted - the buffer is empty and the current address is 0
+    suite.add_test(TestCase::new(
+        "invert_global_delete_all",
+        "global",
+        "v/zzz/d\n.=\n$a\nnew\n.\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Any delimiter other than space or newline can bound the pattern
+    suite.add_test(TestCase::new(
+        "global_pipe_delimiter",
+        "global",
+        "g|foo|d\n,p\nQ\n",
+        "foo\nbar\nbaz\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "invert_global_comma_delimiter",
+        "global",
+        "v,bar,p\nq\n",
+        "foo\nbar\nbaz\n"
+    ));
+
+    // A slash inside the pattern needs no escape with another delimiter
+    suite.add_test(TestCase::new(
+        "global_hash_delimiter_with_slash",
+        "global",
+        "g#/usr/#s,/usr/,/opt/,\n,p\nQ\n",
+        "/usr/bin\n/etc\n/usr/lib\n"
+    ));
+
+    // Zero-width anchored patterns: $ selects every line
+    suite.add_test(TestCase::new(
+        "global_end_anchor_prints_all",
+        "global",
+        "g/$/p\nQ\n",
+        "a\nb\n\nc\n\n"
+    ));
+
+    // ^$ selects only the empty lines
+    suite.add_test(TestCase::new(
+        "global_empty_line_delete",
+        "global",
+        "g/^$/d\n,p\nQ\n",
+        "a\nb\n\nc\n\n"
+    ));
+
+    // x* matches every line with an empty match
+    suite.add_test(TestCase::new(
+        "global_zero_width_substitute",
+        "global",
+        "g/x*/s/x*/Y/g\n,p\nQ\n",
+        "a\nb\n\nc\n\n"
+    ));
+
+    // a\ takes its text from the command list, read once for every matching line
+    suite.add_test(TestCase::new(
+        "global_append_text",
+        "global",
+        "g/x/a\\\nTEXT\n,p\nQ\n",
+        "ax\nb\ncx\nd\n"
+    ));
+
+    // Text lines of the list end in backslashes too; the '.' is optional
+    suite.add_test(TestCase::new(
+        "global_append_text_block",
+        "global",
+        "g/x/a\\\nT1\\\nT2\\\n.\n,p\nQ\n",
+        "ax\nb\ncx\nd\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "global_insert_text",
+        "global",
+        "g/x/i\\\nI\n,p\nQ\n",
+        "ax\nb\ncx\nd\n"
+    ));
+
+    // Several commands run on each line in turn
+    suite.add_test(TestCase::new(
+        "global_multiple_commands",
+        "global",
+        "g/x/s/x/Y/\\\np\n,p\nQ\n",
+        "ax\nb\ncx\nd\n"
+    ));
+
+    // Each line moved to the top keeps its place in the active list
+    suite.add_test(TestCase::new(
+        "global_move_reverse",
+        "global",
+        "g/^/m0\n,p\nQ\n",
+        "1\n2\n3\n4\n"
+    ));
+
+    // Deleting lines ahead of the one being visited drops them from the list
+    suite.add_test(TestCase::new(
+        "global_delete_following_line",
+        "global",
+        "g/x/.,+1d\n,p\nQ\n",
+        "ax\nbx\ncx\nd\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "global_nested_is_an_error",
+        "global",
+        "H\ng/x/g/a/p\nQ\n",
+        "ax\nb\ncx\nd\n"
+    ));
+
+    // G with no pattern at all is rejected for its missing delimiter
+    suite.add_test(TestCase::new(
+        "interactive_global_missing_pattern",
+        "interactive_global",
+        "H\nG\nV\n.=\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // An empty pattern with nothing searched yet has no previous pattern
+    suite.add_test(TestCase::new(
+        "interactive_global_empty_pattern_no_previous",
+        "interactive_global",
+        "H\nG//\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // An empty pattern reuses the last search regex
+    suite.add_test(TestCase::new(
+        "interactive_global_empty_pattern_reuses_last",
+        "interactive_global",
+        "/2/\nG//\ns/line/LINE/p\n,p\nq\n",
+        "line 1\nline 2\nline 3\nline 22\n"
+    ));
+
+    // V with an empty pattern selects the lines not matching the last regex
+    suite.add_test(TestCase::new(
+        "inverse_interactive_global_empty_pattern",
+        "interactive_global",
+        "/2/\nV//\nd\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // c deletes each matching line and puts the same text block in its place
+    suite.add_test(TestCase::new(
+        "global_change_text",
+        "global",
+        "g/old/c\\\nNEW\\\nTWO\n,p\n.=\nu\n,p\nQ\n",
+        "old1\nkeep\nold2\nold3\nkeep2\n"
+    ));
+
+    // An empty change text just deletes the matching lines
+    suite.add_test(TestCase::new(
+        "global_change_empty_text",
+        "global",
+        "g/old/c\\\n.\n,p\n.=\nQ\n",
+        "old1\nkeep\nold2\nold3\nkeep2\n"
+    ));
+
+    // A global leaves the last line it worked on current; = alone still
+    // prints the last line number, .= the current one
+    suite.add_test(TestCase::new(
+        "global_final_current_line",
+        "global",
+        "g/line/p\n=\n.=\nv/line/n\n.=\ng/zzz/p\n.=\ng/line/s/line/L/\n.=\nQ\n",
+        "line a\nother\nline b\nmore\nlast\n"
+    ));
+
+    // n and l as the command list number or list each matching line
+    suite.add_test(TestCase::new(
+        "global_number_and_list",
+        "global",
+        "g/x/n\ng/x/l\nv/x/pn\ng/x/ln\nQ\n",
+        "ax\tq\nb\ncx\n"
+    ));
+
+    // An empty command list, with or without the closing delimiter, is p
+    suite.add_test(TestCase::new(
+        "global_empty_command_list_prints",
+        "global",
+        "g/x/\ng/x\nv/x/\n.=\nQ\n",
+        "ax\tq\nb\ncx\n"
+    ));
+
     suite
 }
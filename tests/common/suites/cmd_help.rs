@@ -37,5 +37,14 @@ pub fn get_test_suite() -> TestSuite {
         "content\n"
     ));
 
+    // GNU ed never clears the error message on success: h explains the
+    // most recent '?', even after later commands succeeded (main_loop.c:626)
+    suite.add_test(TestCase::new(
+        "help_after_error_then_success",
+        "help",
+        "999p\n1p\nh\nq\n",
+        "content\n"
+    ));
+
     suite
 }
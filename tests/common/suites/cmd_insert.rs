@@ -45,5 +45,13 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // 0i inserts at the top, and with no text address 0 becomes current
+    suite.add_test(TestCase::new(
+        "insert_at_address_zero",
+        "insert",
+        "2\n0i\n.\n.=\n0i\nnew\n.\n.=\n,p\nq\n",
+        "one\ntwo\nthree\n"
+    ));
+
     suite
 }
@@ -36,5 +36,22 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // Undo puts back every joined line
+    suite.add_test(TestCase::new(
+        "join_range_then_undo",
+        "join",
+        "2,4j\nu\n,p\nQ\n",
+        "a\nb\nc\nd\n"
+    ));
+
+    // The missing final newline of a binary file belongs to its last line,
+    // which keeps it when lines before it are joined
+    suite.add_test(TestCase::new(
+        "join_before_unterminated_binary_last_line",
+        "join",
+        "1,2j\nw\nq\n",
+        "a\0b\nc\nd"
+    ));
+
     suite
 }
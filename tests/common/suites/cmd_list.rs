@@ -45,5 +45,37 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // GNU ed escapes every '$' in the text, then marks the end with '$'
+    suite.add_test(TestCase::new(
+        "list_embedded_dollar",
+        "list",
+        "l\nq\n",
+        "cost $5 or $6$\n"
+    ));
+
+    // Backslashes, control characters and non-ASCII bytes
+    suite.add_test(TestCase::new(
+        "list_escapes_and_octal",
+        "list",
+        ",l\nq\n",
+        "a\\b\x07\x0b\n\x01\u{e9}\n"
+    ));
+
+    // Long lines are folded with a trailing backslash
+    suite.add_test(TestCase::new(
+        "list_wraps_long_line",
+        "list",
+        "l\n1n\nq\n",
+        &format!("{}$\n", "x".repeat(100))
+    ));
+
+    // The l suffix of p uses the same format
+    suite.add_test(TestCase::new(
+        "list_suffix_dollar",
+        "list",
+        "pl\nq\n",
+        "$HOME\\bin\n"
+    ));
+
     suite
 }
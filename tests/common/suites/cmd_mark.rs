@@ -45,5 +45,22 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // The mark is the character right after k: a bare k has none, and in
+    // "k a" the blank is taken as the mark, leaving "a" as a bad suffix
+    suite.add_test(TestCase::new(
+        "mark_character_position",
+        "mark",
+        "H\n2k\n2k a\n3k\tb\n2ka\n'ap\n1kbn\n'bp\nq\n",
+        "a\nb\nc\n"
+    ));
+
+    // Only lowercase letters are marks, and line 0 cannot be marked
+    suite.add_test(TestCase::new(
+        "mark_invalid_character_or_address",
+        "mark",
+        "H\n3k1\n2kA\n0ka\n'ap\nq\n",
+        "a\nb\nc\n"
+    ));
+
     suite
 }
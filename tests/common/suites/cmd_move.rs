@@ -44,5 +44,60 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // Current line after moving to the top is the last moved line
+    suite.add_test(TestCase::new(
+        "move_to_beginning_current_line",
+        "move",
+        "2,3m0\n.=\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Current line after moving to the end is the last line
+    suite.add_test(TestCase::new(
+        "move_to_end_current_line",
+        "move",
+        "1m$\n.=\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Destination boundaries around the source range 2,4
+    suite.add_test(TestCase::new(
+        "move_range_to_line_before",
+        "move",
+        "2,4m1\n.=\n,p\nq\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "move_range_into_itself",
+        "move",
+        "H\n2,4m3\n2,4m2\n,p\nq\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "move_range_to_its_last_line",
+        "move",
+        "2,4m4\n.=\n,p\nq\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    // Marks stay on their lines as they are moved to the top or past them
+    suite.add_test(TestCase::new(
+        "move_to_top_keeps_marks",
+        "move",
+        "2ka\n4kb\n'bm0\n.=\n'a=\n'b=\n3,4m0\n.=\n'ap\n'bp\n'a=\n'b=\n,p\nQ\n",
+        "one\ntwo\nthree\nfour\n"
+    ));
+
+    // Moved off the end, the unterminated line of a binary file is written
+    // with a newline like any other line
+    suite.add_test(TestCase::new(
+        "move_unterminated_binary_last_line_to_top",
+        "move",
+        "$m0\nw\nq\n",
+        "a\0b\nc\nd"
+    ));
+
     suite
 }
@@ -36,5 +36,13 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // Line numbers are not padded once they reach two digits ("%d\t")
+    suite.add_test(TestCase::new(
+        "numbered_print_two_digit_addresses",
+        "numbered",
+        "8,12n\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\nline 6\nline 7\nline 8\nline 9\nline 10\nline 11\nline 12\n"
+    ));
+
     suite
 }
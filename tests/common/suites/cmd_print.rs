@@ -61,5 +61,29 @@ pub fn get_test_suite() -> TestSuite {
         "Line 1\nLine 2\nLine 3\n"
     ));
 
+    // z with a count and print suffixes
+    suite.add_test(TestCase::new(
+        "scroll_count_with_suffix",
+        "print",
+        "1z2n\n2z1l\nq\n",
+        "line 1\nline\t2\nline 3\n"
+    ));
+
+    // A z count is remembered: the bare z shows another 2-line window
+    suite.add_test(TestCase::new(
+        "scroll_reuses_last_count",
+        "print",
+        "1z2\nz\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\nline 6\n"
+    ));
+
+    // Printing makes the last printed line current
+    suite.add_test(TestCase::new(
+        "print_sets_current_line",
+        "print",
+        "1p\n.=\n1,2n\n.=\n1l\n.\nq\n",
+        "one\ntwo\nthree\n"
+    ));
+
     suite
 }
@@ -28,5 +28,40 @@ pub fn get_test_suite() -> TestSuite {
         "content\n"
     ));
 
+    // The prompt goes to stdout before each command is read
+    suite.add_test(TestCase::new(
+        "prompt_placement_on_stdout",
+        "prompt",
+        "P\n1p\nq\n",
+        "content\n"
+    ));
+
+    // -p sets the prompt string and turns the prompt on
+    suite.add_test(TestCase::new_with_options(
+        "prompt_option_string",
+        "prompt",
+        &["-p", ":"],
+        "1p\nq\n",
+        "content\n"
+    ));
+
+    suite.add_test(TestCase::new_with_options(
+        "prompt_option_toggled_off",
+        "prompt",
+        &["-p", "> "],
+        "P\n1p\nP\n1p\nq\n",
+        "content\n"
+    ));
+
+    // The prompt is printed as given: backslash escapes and printf
+    // conversions in it are not interpreted
+    suite.add_test(TestCase::new_with_options(
+        "prompt_option_escapes_literal",
+        "prompt",
+        &["-p", "\\t%s> "],
+        "1p\nP\nP\nq\n",
+        "content\n"
+    ));
+
     suite
 }
@@ -37,5 +37,60 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\n"
     ));
 
+    // The initial file load is not a modification, so q exits cleanly
+    suite.add_test(TestCase::new(
+        "read_initial_load_unmodified",
+        "read",
+        "q\n",
+        "line 1\nline 2\n"
+    ));
+
+    // r that adds lines marks the buffer modified, so q warns first
+    suite.add_test(TestCase::new(
+        "read_marks_buffer_modified",
+        "read",
+        "r\nq\nQ\n",
+        "line 1\nline 2\n"
+    ));
+
+    // r of an empty file adds nothing and leaves the buffer unmodified
+    suite.add_test(TestCase::new(
+        "read_empty_file_unmodified",
+        "read",
+        "r /dev/null\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // A bare r reads after the last line, wherever the current line is
+    suite.add_test(TestCase::new(
+        "read_no_address_after_last_line",
+        "read",
+        "2\nr !echo extra\n.=\n,p\nQ\n",
+        "line 1\nline 2\nline 3\nline 4\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "read_after_current_line",
+        "read",
+        "2\n.r !echo extra\n.=\n,p\nQ\n",
+        "line 1\nline 2\nline 3\nline 4\n"
+    ));
+
+    // The last line read becomes current, wherever the text was inserted
+    suite.add_test(TestCase::new(
+        "read_sets_current_to_last_line_read",
+        "read",
+        "1r\n.=\n0r\n.=\n2r !printf 'x\\ny\\n'\n.=\n$=\n,n\nQ\n",
+        "a\nb\nc\n"
+    ));
+
+    // Multibyte characters count as all their bytes
+    suite.add_test(TestCase::new(
+        "read_multibyte_byte_counts",
+        "read",
+        "r\n0r !printf 'caf\\303\\251\\n'\n.=\nE\n,n\nq\n",
+        "café\nnaïve 日本語\n"
+    ));
+
     suite
 }
@@ -53,5 +53,45 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // Empty RE repeats the last search forward (//) and backward (??)
+    suite.add_test(TestCase::new(
+        "search_repeat_last_regex",
+        "search",
+        "1\n/foo/\n//\n??\nq\n",
+        "foo 1\nbar\nfoo 2\nfoo 3\n"
+    ));
+
+    // Search wraps around to the current line when it is the only match
+    suite.add_test(TestCase::new(
+        "search_wraps_to_current_line",
+        "search",
+        "2\n/bar/\n??\nq\n",
+        "foo 1\nbar\nfoo 2\n"
+    ));
+
+    // The pattern of a substitution is reused by a later empty search
+    suite.add_test(TestCase::new(
+        "search_repeat_after_substitute",
+        "search",
+        "1s/foo/FOO/\n//\nQ\n",
+        "foo 1\nbar\nfoo 2\n"
+    ));
+
+    // Two searches as the ends of a range
+    suite.add_test(TestCase::new(
+        "search_range_delete",
+        "search",
+        "/foo/,/bar/d\n,p\nQ\n",
+        "a\nfoo\nb\nbar\nc\n"
+    ));
+
+    // With ';' the second search starts from the first match
+    suite.add_test(TestCase::new(
+        "search_range_semicolon",
+        "search",
+        "1;/b/p\n/foo/;/b/p\nQ\n",
+        "a\nfoo\nb\nbar\nc\n"
+    ));
+
     suite
 }
@@ -47,5 +47,128 @@ pub fn get_test_suite() -> TestSuite {
         "zebra\napple\nbanana\n"
     ));
 
+    // Shell output without a trailing newline reports "Newline appended"
+    suite.add_test(TestCase::new(
+        "shell_read_unterminated_output",
+        "shell",
+        "r !printf 'a\\nb'\nr !printf 'c\\nd\\n'\n,p\nw\nq\n",
+        ""
+    ));
+
+    // r !command reports the bytes read into the buffer
+    suite.add_test(TestCase::new(
+        "shell_read_byte_count",
+        "shell",
+        "r !printf 'a\\nb\\n'\n,p\nQ\n",
+        "first\n"
+    ));
+
+    // Under -s, ! prints no "!" line but still echoes an expanded
+    // command, and filters and r/w through a command print no byte counts
+    suite.add_test(TestCase::new_with_options(
+        "shell_scripted_escape_quiet",
+        "shell",
+        &["-s"],
+        "!echo hi\n!!\nQ\n",
+        "line 1\n"
+    ));
+
+    suite.add_test(TestCase::new_with_options(
+        "shell_scripted_filter_and_read_quiet",
+        "shell",
+        &["-s"],
+        "1,2!sort -r\nr !echo x\nw !cat\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // A bare '!' runs an empty command; only '!!' needs a previous one
+    suite.add_test(TestCase::new(
+        "shell_bare_bang",
+        "shell",
+        "!\n!   \nh\nq\n",
+        "line 1\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "shell_repeat_without_previous_command",
+        "shell",
+        "!!\nh\nq\n",
+        "line 1\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "shell_repeat_bare_bang",
+        "shell",
+        "!\n!!\nq\n",
+        "line 1\n"
+    ));
+
+    suite.add_test(TestCase::new_with_options(
+        "shell_repeat_bare_bang_traditional",
+        "shell",
+        &["-G"],
+        "!\n!!\nh\nq\n",
+        "line 1\n"
+    ));
+
+    suite.add_test(TestCase::new_with_options(
+        "shell_restricted",
+        "shell",
+        &["-r"],
+        "!echo hi\nh\nq\n",
+        "line 1\n"
+    ));
+
+    // Restricted mode refuses other directories and shell commands as the
+    // file operand of e, r and w too
+    suite.add_test(TestCase::new_with_options(
+        "shell_restricted_file_operands",
+        "shell",
+        &["-r"],
+        "e /etc/passwd\nh\nr ../x\nh\nw sub/x\nh\nr !ls\nh\nw !cat\nh\nq\n",
+        "line 1\n"
+    ));
+
+    // The command is kept with its trailing blanks for !!
+    suite.add_test(TestCase::new(
+        "shell_repeat_trailing_blanks",
+        "shell",
+        "!   \n!!\n!echo a  \n!!\nq\n",
+        "line 1\n"
+    ));
+
+    // The current line is the last line of the filtered output
+    suite.add_test(TestCase::new(
+        "shell_filter_sets_current_line",
+        "shell",
+        "1,3!sort\n.=\n2,3!sed p\n.=\nq\n",
+        "e\nd\nc\nb\na\n"
+    ));
+
+    // A filter with no output leaves the current line before the range
+    suite.add_test(TestCase::new(
+        "shell_filter_empty_output_current_line",
+        "shell",
+        "2,3!sed d\n.=\n,p\nq\n",
+        "e\nd\nc\nb\na\n"
+    ));
+
+    // Command output is read like a file: a NUL makes the buffer binary,
+    // and its unterminated last line is written back as it was read
+    suite.add_test(TestCase::new(
+        "shell_read_binary_unterminated_output",
+        "shell",
+        "r !printf 'a\\000b\\nc'\nw\nq\n",
+        "x\n"
+    ));
+
+    // The byte count includes the CR of each CR/LF line
+    suite.add_test(TestCase::new(
+        "shell_read_crlf_byte_count",
+        "shell",
+        "r !printf 'a\\r\\nb\\n'\n,l\nq\n",
+        "x\n"
+    ));
+
     suite
 }
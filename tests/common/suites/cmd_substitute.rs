@@ -71,5 +71,212 @@ pub fn get_test_suite() -> TestSuite {
         "old 1\nkeep\nold 2\n"
     ));
 
+    // Any character other than space or newline can delimit the pattern
+    suite.add_test(TestCase::new(
+        "substitute_pipe_delimiter",
+        "substitute",
+        "1s|/usr/bin|/opt/bin|\n,p\nQ\n",
+        "/usr/bin/ed\nkeep\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_hash_delimiter_global",
+        "substitute",
+        "s#o#0#gp\nQ\n",
+        "foo boo zoo\n"
+    ));
+
+    // An escaped delimiter is part of the pattern and the replacement
+    suite.add_test(TestCase::new(
+        "substitute_escaped_delimiter",
+        "substitute",
+        "s/a\\/b/c\\/d/p\nQ\n",
+        "path a/b here\n"
+    ));
+
+    // An escaped delimiter in the replacement is a literal character
+    suite.add_test(TestCase::new(
+        "substitute_replacement_escaped_slash",
+        "substitute",
+        "s/a/b\\/c/\n,p\nQ\n",
+        "xay\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_replacement_escaped_pipe",
+        "substitute",
+        "s|a|b\\|c|\n,p\nQ\n",
+        "xay\n"
+    ));
+
+    // A range where no line matches fails with "No match"
+    suite.add_test(TestCase::new(
+        "substitute_range_no_match",
+        "substitute",
+        "1,5s/z/y/g\nh\n,p\nq\n",
+        "a\nxb\nc\nxd\ne\n"
+    ));
+
+    // A range where only some lines match succeeds; the last changed line is current
+    suite.add_test(TestCase::new(
+        "substitute_range_partial_match",
+        "substitute",
+        "1,5s/x/y/g\n.=\n,p\nQ\n",
+        "a\nxb\nc\nxd\ne\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_binary_line_keeps_nuls",
+        "substitute",
+        "1s/c/Z/g\n,l\nw\nq\n",
+        "ab\0cd\0c\nxx\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_binary_nul_as_newline",
+        "substitute",
+        "1s/b[[:space:]]c/-/\n2s/./X/g\n,l\nQ\n",
+        "ab\0cd\nx\0y\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_bracket_expression_before_delimiter",
+        "substitute",
+        "s/[b]/X/p\nq\n",
+        "a[b]c\n"
+    ));
+
+    // An empty replacement deletes the match; flags after it still apply
+    suite.add_test(TestCase::new(
+        "substitute_empty_replacement",
+        "substitute",
+        "2s/foo//\n,p\nq\n",
+        "a b  c d\nfoo foo\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_empty_replacement_global",
+        "substitute",
+        "1s/ //g\n,p\nq\n",
+        "a b  c d\nfoo foo\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "substitute_empty_replacement_count_and_print",
+        "substitute",
+        "2s/o//2p\nq\n",
+        "a b  c d\nfoo foo\n"
+    ));
+
+    // A bare s repeats the last substitution on a new range
+    suite.add_test(TestCase::new(
+        "substitute_repeat_last",
+        "substitute",
+        "1s/o/0/\n2s\n3sg\n,p\nq\n",
+        "one\ntwoo\nfoo boo\n"
+    ));
+
+    // Repeat suffixes: p toggles printing, a count picks the match, r uses the last search
+    suite.add_test(TestCase::new(
+        "substitute_repeat_suffixes",
+        "substitute",
+        "1s/o/0/\n3s2p\n/b/\n3sr\n,p\nq\n",
+        "one\ntwoo\nfoo boo\n"
+    ));
+
+    // With nothing to repeat, or a suffix given twice, the repeat form fails
+    suite.add_test(TestCase::new(
+        "substitute_repeat_errors",
+        "substitute",
+        "H\ns\n1s/o/0/\n2sgg\n2spp\n2s2g\n,p\nq\n",
+        "one\ntwoo\n"
+    ));
+
+    // \< and \> anchor a match at the start and end of a word
+    suite.add_test(TestCase::new(
+        "substitute_word_boundaries",
+        "substitute",
+        ",s/\\<foo\\>/X/g\n,s/\\<f/Y/g\n,s/o\\>/Z/g\n,p\nq\n",
+        "foo food afoo foo\nfoo_x foo-x\n"
+    ));
+
+    // Word anchors work in searches and global commands too
+    suite.add_test(TestCase::new(
+        "substitute_word_boundaries_in_addresses",
+        "substitute",
+        "g/\\<cd\\>/s//X/p\n/\\<g/p\n?ab\\>?s/b\\>/B/p\nq\n",
+        "ab cd-ef\nabcd\n  gh\n"
+    ));
+
+    // An escaped newline in the replacement splits the line; at the end
+    // of the line it leaves an empty line after the text
+    suite.add_test(TestCase::new(
+        "substitute_newline_at_end_of_line",
+        "substitute",
+        "1s/x$/y\\\n/\n.=\n$=\n/c/s/$/\\\n/\n.=\n,l\nQ\n",
+        "ax\nb\ncx\n"
+    ));
+
+    // Several newlines make several lines; the last one is current and u
+    // joins them back
+    suite.add_test(TestCase::new(
+        "substitute_newlines_split_line",
+        "substitute",
+        "2s/b/1\\\n2\\\n3/p\n.=\n$=\nu\n,l\nQ\n",
+        "ax\nb\ncx\n"
+    ));
+
+    // Lines split early in a range push the rest of the range down
+    suite.add_test(TestCase::new(
+        "substitute_newlines_in_range",
+        "substitute",
+        "1,2s/-/\\\n/gp\n.=\n,l\nQ\n",
+        "a-b-c\nd-e\nf-g\n"
+    ));
+
+    // Inside a global command the replacement runs on into the next line
+    // of the command list
+    suite.add_test(TestCase::new(
+        "substitute_newline_in_global",
+        "substitute",
+        "g/x/s/x/X\\\nY/\n,l\nQ\n",
+        "ax\nb\ncx\n"
+    ));
+
+    // & is the matched text; a backslash makes & or a digit without a
+    // group literal
+    suite.add_test(TestCase::new(
+        "substitute_ampersand_and_escapes",
+        "substitute",
+        ",s/-/[&]\\&\\2/g\n,p\nQ\n",
+        "a-b\nc-d-e\n"
+    ));
+
+    // An empty match where the last one ended is replaced once; a second
+    // one in a row is an infinite substitution loop
+    suite.add_test(TestCase::new(
+        "substitute_empty_matches",
+        "substitute",
+        "H\n1s/^/#/g\n2s/$/#/g\n3s/b*/-/3\n4s/x*/-/g\n,p\nQ\n",
+        "abc\nabc\nabc\nxa\n"
+    ));
+
+    // A replacement of a lone % is the last replacement; % with more text
+    // is literal, and with no earlier substitution it is an error
+    suite.add_test(TestCase::new(
+        "substitute_percent_repeats_replacement",
+        "substitute",
+        "H\n2s/b/%/\n1s/a/x/\n2s/b/%/\n3s/b/%%/\ng/c/s/c/%\n,p\nQ\n",
+        "a\nb\nab\nc\n"
+    ));
+
+    // A substitution replaces the line, and the new one ends in a newline
+    suite.add_test(TestCase::new(
+        "substitute_terminates_binary_last_line",
+        "substitute",
+        "$s/d/e/\nw\nq\n",
+        "a\0b\nc\nd"
+    ));
+
     suite
 }
@@ -45,5 +45,44 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // Current line after copying to the top is the copied line (address 1)
+    suite.add_test(TestCase::new(
+        "transfer_to_beginning_current_line",
+        "transfer",
+        "3t0\n.=\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Current line after copying to the end is the new last line
+    suite.add_test(TestCase::new(
+        "transfer_to_end_current_line",
+        "transfer",
+        "1,2t$\n.=\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Copying into the source range duplicates the original lines
+    suite.add_test(TestCase::new(
+        "transfer_range_into_itself",
+        "transfer",
+        "2,4t3\n.=\n,p\nQ\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "transfer_range_after_its_first_line",
+        "transfer",
+        "1,4t1\n,p\nu\n,p\nQ\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    // Copying to the top shifts marked lines down with the rest
+    suite.add_test(TestCase::new(
+        "transfer_to_top_keeps_marks",
+        "transfer",
+        "3ka\n1t0\n.=\n'a=\n'ap\n2,3t0\n.=\n'a=\n,p\nQ\n",
+        "one\ntwo\nthree\nfour\n"
+    ));
+
     suite
 }
@@ -45,5 +45,120 @@ pub fn get_test_suite() -> TestSuite {
         "original\n"
     ));
 
+    // Undo restores the current line to where it was before the command (buffer.c undo)
+    suite.add_test(TestCase::new(
+        "undo_delete_current_line",
+        "undo",
+        "2\n3d\nu\n.=\n,p\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_append_current_line",
+        "undo",
+        "2\n3a\nnew line\n.\nu\n.=\n,p\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_change_current_line",
+        "undo",
+        "2\n3c\nchanged\n.\nu\n.=\n,p\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_substitute_current_line",
+        "undo",
+        "2\n3s/line/LINE/\nu\n.=\n,p\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_move_current_line",
+        "undo",
+        "2\n3m5\nu\n.=\n,p\nq\n",
+        "line 1\nline 2\nline 3\nline 4\nline 5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_global_delete",
+        "undo",
+        "2\ng/x/d\nu\n.=\n,p\nq\n",
+        "x1\na\nx2\nb\nx3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_global_substitute",
+        "undo",
+        "v/x/s/$/!/\nu\n,p\nq\n",
+        "x1\na\nx2\nb\nx3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_interactive_global",
+        "undo",
+        "G/x/\ns/x/Y/\n\ns/x/Z/\nu\n,p\nq\n",
+        "x1\na\nx2\nb\nx3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_reverts_only_last_command",
+        "undo",
+        "1d\n$d\nu\n,p\nQ\n",
+        "x1\na\nx2\nb\nx3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_command_after_global",
+        "undo",
+        "g/x/s/x/y/\n1d\nu\n,p\nQ\n",
+        "x1\na\nx2\nb\nx3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_after_write",
+        "undo",
+        "1d\nw\nu\n,p\nQ\n",
+        "x1\na\nx2\nb\nx3\n"
+    ));
+
+    // Undoing t and m puts marks back on their lines' old positions
+    suite.add_test(TestCase::new(
+        "undo_move_restores_marks",
+        "undo",
+        "2ka\n2m4\n'a=\nu\n'a=\n'ap\nQ\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_copy_to_top_restores_marks",
+        "undo",
+        "3kb\n1t0\n'b=\nu\n'b=\n'bp\nQ\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_global_move_restores_marks",
+        "undo",
+        "2ka\n3kb\ng/./m0\n'a=\n'b=\nu\n'a=\n'b=\n,p\nQ\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "undo_delete_restores_marks",
+        "undo",
+        "2ka\n2d\nu\n'a=\n'ap\nQ\n",
+        "1\n2\n3\n4\n5\n"
+    ));
+
+    // Undoing a substitution brings back the unterminated last line
+    suite.add_test(TestCase::new(
+        "undo_restores_unterminated_binary_last_line",
+        "undo",
+        "$s/d/e/\nu\nw\nq\n",
+        "a\0b\nc\nd"
+    ));
+
     suite
 }
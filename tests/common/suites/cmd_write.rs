@@ -54,5 +54,68 @@ pub fn get_test_suite() -> TestSuite {
         "delete this\nkeep this\n"
     ));
 
+    // W appends the whole buffer when no address is given; twice gives six lines
+    suite.add_test(TestCase::new(
+        "write_append_twice",
+        "write",
+        "W out.txt\nW out.txt\n$r out.txt\n,n\nQ\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // W with a range appends only those lines
+    suite.add_test(TestCase::new(
+        "write_append_range",
+        "write",
+        "1,2W out.txt\n1,2W out.txt\n$r out.txt\n,p\nQ\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // W of part of a modified buffer leaves it modified (GNU ed main_loop.c:709)
+    suite.add_test(TestCase::new(
+        "write_append_range_keeps_modified",
+        "write",
+        "a\nfour\n.\n1,2W out.txt\nq\nQ\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // Writing to /dev/stdout prints the lines followed by the byte count
+    suite.add_test(TestCase::new(
+        "write_to_dev_stdout",
+        "write",
+        "w /dev/stdout\n2w /dev/stdout\nf\nq\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // A device that cannot take the data fails the write
+    suite.add_test(TestCase::new(
+        "write_to_dev_full_fails",
+        "write",
+        "H\nw /dev/full\nq\nQ\n",
+        "one\ntwo\n"
+    ));
+
+    // wq writes the buffer and quits; wQ quits even if part was written
+    suite.add_test(TestCase::new(
+        "write_quit",
+        "write",
+        "2d\nwq\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "write_range_quit_warns",
+        "write",
+        "H\n1d\n1wq\n1wQ\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // Byte counts are in bytes, not characters: "café\n" is 6
+    suite.add_test(TestCase::new(
+        "write_multibyte_byte_counts",
+        "write",
+        "w out.txt\n1w out.txt\n2W out.txt\na\nüber\n.\nw\n,l\nq\n",
+        "café\nnaïve 日本語\n"
+    ));
+
     suite
 }
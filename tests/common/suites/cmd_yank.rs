@@ -36,5 +36,52 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\nline 3\n"
     ));
 
+    // e and E start a new scratch buffer, which empties the yank buffer
+    suite.add_test(TestCase::new(
+        "yank_cleared_by_edit",
+        "yank",
+        "1y\nE\nx\nh\n,p\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // A yank survives navigation and commands that delete nothing
+    suite.add_test(TestCase::new(
+        "yank_survives_navigation",
+        "yank",
+        "1,2y\n3\n/line 1/\n$x\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // d (like c and j) yanks the lines it deletes, replacing the earlier yank
+    suite.add_test(TestCase::new(
+        "yank_then_delete_puts_deleted_lines",
+        "yank",
+        "1,2y\n3d\n0x\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // Deleting everything leaves the deleted lines to put back
+    suite.add_test(TestCase::new(
+        "yank_then_delete_all_then_put",
+        "yank",
+        "1y\n,d\nx\n,p\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "yank_then_edit_nothing_to_put",
+        "yank",
+        "1,2y\ne\nx\nh\n,p\nQ\n",
+        "line 1\nline 2\n"
+    ));
+
+    // Marks stay on their lines when x puts lines before them
+    suite.add_test(TestCase::new(
+        "put_keeps_marks_on_their_lines",
+        "yank",
+        "2ka\n1y\n0x\n'a=\n3d\nu\n'a=\nQ\n",
+        "a\nb\nc\n"
+    ));
+
     suite
 }
@@ -46,6 +46,28 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\nline 2\n"
     ));
 
+    // Reversed ranges must fail with "Invalid address" for every range command
+    suite.add_test(TestCase::new(
+        "error_reversed_range_delete",
+        "error",
+        "3,1d\nh\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_reversed_range_move",
+        "error",
+        "3,1m0\nh\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_reversed_range_transfer",
+        "error",
+        "3,1t0\nh\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
     // Empty buffer operations
     suite.add_test(TestCase::new(
         "error_empty_buffer_print",
@@ -61,6 +83,22 @@ pub fn get_test_suite() -> TestSuite {
         ""
     ));
 
+    // With no current line, p, n and l all fail the same way, whether the
+    // buffer started empty or was emptied by 1,$d
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_print_number_list",
+        "error",
+        "H\np\nn\nl\n.p\n.n\n.l\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_print_number_list_after_delete_all",
+        "error",
+        "H\n1,$d\np\nn\nl\n.p\n.n\n.l\n=\nQ\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
     // Operation without address when required
     suite.add_test(TestCase::new(
         "error_no_address_for_print",
@@ -77,5 +115,244 @@ pub fn get_test_suite() -> TestSuite {
         "line 1\n"
     ));
 
+    // Print-family commands accept only l, n, p as suffixes (GNU get_command_suffix)
+    suite.add_test(TestCase::new(
+        "error_print_count_suffix",
+        "error",
+        "p3\nh\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_list_invalid_suffix",
+        "error",
+        "lX\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_number_comma_suffix",
+        "error",
+        "n,\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // Writing to a path that can't be created reports the OS error and keeps the buffer modified
+    suite.add_test(TestCase::new(
+        "error_write_unwritable_path",
+        "error",
+        "1d\nw /no/such/dir/file\nh\nq\nQ\n",
+        "line 1\nline 2\n"
+    ));
+
+    // u takes no address (GNU ed unexpected_address)
+    suite.add_test(TestCase::new(
+        "error_address_on_undo",
+        "error",
+        "1d\n2u\nh\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_range_on_undo",
+        "error",
+        "1d\n1,2u\nh\nu\n,p\nq\n",
+        "line 1\nline 2\nline 3\n"
+    ));
+
+    // h explains why a substitute pattern was rejected
+    suite.add_test(TestCase::new(
+        "error_substitute_unbalanced_bracket",
+        "error",
+        "s/[/x/\nh\n,p\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_substitute_escaped_delimiter_no_match",
+        "error",
+        "s/\\/x/\nh\n,p\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // z takes a count starting with 1-9; z0 and other suffixes are rejected
+    suite.add_test(TestCase::new(
+        "error_scroll_zero_count",
+        "error",
+        "1z0\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_scroll_invalid_suffix",
+        "error",
+        "1zx\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // A file name longer than PATH_MAX is rejected before any I/O
+    let long_name = "a".repeat(5000);
+    suite.add_test(TestCase::new(
+        "error_write_filename_too_long",
+        "error",
+        &format!("w {}\nh\nq\n", long_name),
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_edit_filename_too_long_keeps_buffer",
+        "error",
+        &format!("E {}\nh\n,p\nq\n", long_name),
+        "line 1\nline 2\n"
+    ));
+
+    // Malformed addresses report GNU ed's specific messages through h
+    suite.add_test(TestCase::new(
+        "error_address_mark_not_lowercase",
+        "error",
+        "'A\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_address_mark_missing",
+        "error",
+        "'\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_address_mark_unset",
+        "error",
+        "'z\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_address_search_no_previous_pattern",
+        "error",
+        "//\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_address_search_no_match",
+        "error",
+        "/nowhere/\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_address_number_out_of_range",
+        "error",
+        "99999999999999999999p\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_address_past_end",
+        "error",
+        "1,9p\nh\nq\n",
+        "line 1\nline 2\n"
+    ));
+
+    // Every address form is invalid in an empty buffer, where . and $ are 0
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_dot",
+        "error",
+        ".\nh\n.=\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_dollar",
+        "error",
+        "$\nh\n.=\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_plus",
+        "error",
+        "+\nh\n+p\nh\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_minus",
+        "error",
+        "-\nh\n-p\nh\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_newline",
+        "error",
+        "\nh\n.=\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_ranges",
+        "error",
+        "1p\n,p\n;p\n.,$d\nh\n.=\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_global",
+        "error",
+        "g/x/p\nh\nv/x/p\nh\nq\n",
+        ""
+    ));
+
+    suite.add_test(TestCase::new(
+        "error_empty_buffer_mark",
+        "error",
+        "ka\nh\nk\nh\nq\n",
+        ""
+    ));
+
+    // Every letter GNU ed leaves unassigned is an unknown command
+    suite.add_test(TestCase::new(
+        "error_unknown_command_letters",
+        "error",
+        "H\nb\no\nA\nB\nC\nD\nF\nI\nJ\nK\nL\nM\nN\nO\nR\nS\nT\nU\nX\nY\nZ\n,p\nq\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // Commands that take a print suffix reject anything but l, n and p
+    suite.add_test(TestCase::new(
+        "error_invalid_suffix_letters",
+        "error",
+        "H\nax\nix\n2cx\n2dx\n2yx\n2xx\nhx\nHx\nPx\nux\n=x\n1,2jx\n1kax\n1m3x\n1t3x\nqx\nQx\n,p\nq\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // File commands need a blank before the filename
+    suite.add_test(TestCase::new(
+        "error_unexpected_suffix_letters",
+        "error",
+        "H\nrx\nex\nEx\nfx\nwx\nWx\nwqx\nf\n,p\nq\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // A p, l or n suffix prints the line each command leaves current
+    suite.add_test(TestCase::new(
+        "print_suffix_letters",
+        "error",
+        "2dp\nan\nnew\n.\n1m$l\n1t0p\n1,2jp\n1kap\n=p\nyp\nxn\nup\nip\n.\nQ\n",
+        "one\ntwo\nthree\n"
+    ));
+
+    // A number too big for an int is out of range; an offset that pushes
+    // an address past the int range is an invalid address
+    suite.add_test(TestCase::new(
+        "error_address_overflow",
+        "error",
+        "H\n99999999999p\n2147483647+1p\n1+2147483647p\n$+99999999999p\n2147483647 1p\n-2147483647--p\n1;+99999999999999999999p\n2p\nq\n",
+        "a\nb\nc\n"
+    ));
+
     suite
 }
@@ -51,5 +51,61 @@ pub fn get_test_suite() -> TestSuite {
         "q\n"
     ));
 
+    // Test: The launch filename is the default even though nothing was read;
+    // re-editing the default file shows the bare w wrote there
+    suite.add_test(TestCase::new_nonexistent_file(
+        "launch_filename_is_default_after_failed_read",
+        "file_creation",
+        "a\nsaved text\n.\nw\nf\ne\n,p\nq\n"
+    ));
+
+    // The startup byte count is the file size, plus the newline appended
+    // to an unterminated text file (GNU ed io.c:229)
+    suite.add_test(TestCase::new(
+        "launch_byte_count_terminated",
+        "file_creation",
+        "q\n",
+        "one\ntwo\n"
+    ));
+
+    suite.add_test(TestCase::new(
+        "launch_byte_count_unterminated",
+        "file_creation",
+        ",p\nq\n",
+        "one\ntwo"
+    ));
+
+    suite.add_test(TestCase::new(
+        "launch_byte_count_unterminated_binary",
+        "file_creation",
+        ",l\nq\n",
+        "one\ntw\0o"
+    ));
+
+    suite.add_test(TestCase::new(
+        "launch_byte_count_crlf",
+        "file_creation",
+        ",l\nw\nr\n,l\nQ\n",
+        "one\r\ntwo\r\n"
+    ));
+
+    // --strip-trailing-cr drops the CRs and leaves them out of the count
+    suite.add_test(TestCase::new_with_options(
+        "launch_byte_count_crlf_stripped",
+        "file_creation",
+        &["--strip-trailing-cr"],
+        ",l\nw\nr\n,l\nQ\n",
+        "one\r\ntwo\r\n"
+    ));
+
+    // A directory operand is reported and ed starts with an empty buffer
+    // (the buffer stays unmodified: GNU ed would then warn that the file
+    // is read-only)
+    suite.add_test(TestCase::new_directory_operand(
+        "launch_directory_opens_empty_buffer",
+        "file_creation",
+        "=\nh\nq\n"
+    ));
+
     suite
 }
@@ -13,6 +13,7 @@
 // Command-specific test suites (one file per GNU ed command - alphabetical)
 pub mod cmd_append;      // a - append text after line
 pub mod cmd_change;      // c - change lines
+pub mod cmd_comment;     // # - comment
 pub mod cmd_delete;      // d - delete lines
 pub mod cmd_edit;        // e,E - edit file
 pub mod cmd_equals;      // = - show line number
@@ -51,6 +52,7 @@ pub fn get_all_test_suites() -> Vec<TestSuite> {
         // Command-specific test suites (alphabetical by command letter)
         cmd_append::get_test_suite(),
         cmd_change::get_test_suite(),
+        cmd_comment::get_test_suite(),
         cmd_delete::get_test_suite(),
         cmd_edit::get_test_suite(),
         cmd_equals::get_test_suite(),
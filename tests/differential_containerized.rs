@@ -61,7 +61,14 @@ impl EdDifferentialTester {
         println!("[{}/∞] Running: {}", self.test_count, test_case.name);
 
         // Handle file creation based on test_case.file_should_not_exist
-        let (temp_path, _temp_file_guard) = if test_case.file_should_not_exist {
+        let mut _temp_dir_guard = None;
+        let (temp_path, _temp_file_guard) = if test_case.file_is_directory {
+            // For directory operand tests: an empty directory stands in for the file
+            let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+            let temp_path = temp_dir.path().to_path_buf();
+            _temp_dir_guard = Some(temp_dir);
+            (temp_path, None)
+        } else if test_case.file_should_not_exist {
             // For non-existent file tests: create temp file but delete it before running ed
             // We need to create it first so we have a valid path in a writable directory
             let temp_file = NamedTempFile::new().expect("Failed to create temp file");
@@ -79,19 +86,19 @@ impl EdDifferentialTester {
         };
 
         // Run GNU ed in isolated container
-        let gnu_output = self.run_gnu_ed_container(&test_case.commands, &temp_path);
+        let gnu_output = self.run_gnu_ed_container(&test_case.commands, &test_case.options, &temp_path);
         let gnu_final = fs::read_to_string(&temp_path).unwrap_or_default();
 
         // Reset file for Rust test
         if test_case.file_should_not_exist {
             // Delete the file if it was created, so rust-ed gets the same non-existent state
             let _ = fs::remove_file(&temp_path);
-        } else {
+        } else if !test_case.file_is_directory {
             fs::write(&temp_path, &test_case.input_text).expect("Failed to reset temp file");
         }
 
         // Run rust-ed in isolated container (SYMMETRIC - identical environment)
-        let rust_output = self.run_rust_ed_container(&test_case.commands, &temp_path);
+        let rust_output = self.run_rust_ed_container(&test_case.commands, &test_case.options, &temp_path);
         let rust_final = fs::read_to_string(&temp_path).unwrap_or_default();
 
         // Cleanup temp file if needed
@@ -154,7 +161,7 @@ impl EdDifferentialTester {
     }
 
     /// Run GNU ed in isolated Docker container
-    fn run_gnu_ed_container(&self, commands: &str, file_path: &std::path::Path) -> Output {
+    fn run_gnu_ed_container(&self, commands: &str, options: &[String], file_path: &std::path::Path) -> Output {
         // Get the parent directory and filename
         let parent_dir = file_path.parent().unwrap_or_else(|| std::path::Path::new("/tmp"));
         let filename = file_path.file_name().unwrap().to_str().unwrap();
@@ -165,6 +172,7 @@ impl EdDifferentialTester {
             .args(&["--user", "1000:1000"])  // Run as host user to allow file writes
             .args(&["-v", &format!("{}:/tmp", parent_dir.display())])
             .arg(&self.gnu_ed_container)
+            .args(options)
             .arg(&container_path)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
@@ -184,7 +192,7 @@ impl EdDifferentialTester {
     }
 
     /// Run rust-ed in isolated Docker container (SYMMETRIC with GNU ed)
-    fn run_rust_ed_container(&self, commands: &str, file_path: &std::path::Path) -> Output {
+    fn run_rust_ed_container(&self, commands: &str, options: &[String], file_path: &std::path::Path) -> Output {
         // Get the parent directory and filename
         let parent_dir = file_path.parent().unwrap_or_else(|| std::path::Path::new("/tmp"));
         let filename = file_path.file_name().unwrap().to_str().unwrap();
@@ -195,6 +203,7 @@ impl EdDifferentialTester {
             .args(&["--user", "1000:1000"])  // Run as host user to allow file writes
             .args(&["-v", &format!("{}:/tmp", parent_dir.display())])
             .arg(&self.rust_ed_container)
+            .args(options)
             .arg(&container_path)
             .stdin(std::process::Stdio::piped())
             .stdout(std::process::Stdio::piped())
@@ -409,6 +418,14 @@ fn test_containerized_cmd_change() {
     tester.run_test_suites(&["cmd_change"]);
 }
 
+/// Test only comment command (#)
+#[test]
+fn test_containerized_cmd_comment() {
+    let mut tester = EdDifferentialTester::new();
+    println!("🐳 Testing comment command (#) - containerized");
+    tester.run_test_suites(&["cmd_comment"]);
+}
+
 /// Test only insert command (i)
 #[test]
 fn test_containerized_cmd_insert() {
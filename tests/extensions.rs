@@ -0,0 +1,450 @@
+//! rust-ed extension tests
+//! Behavior the differential suites cannot cover, either because GNU ed
+//! does not have it or because it needs its own working directory - these
+//! run the rust-ed binary directly.
+
+use assert_cmd::Command;
+use std::fs;
+use tempfile::NamedTempFile;
+
+/// Create a file with the given content and return its handle and path
+fn input_file(content: &str) -> (NamedTempFile, String) {
+    let file = NamedTempFile::new().unwrap();
+    fs::write(file.path(), content).unwrap();
+    let path = file.path().to_str().unwrap().to_string();
+    (file, path)
+}
+
+fn rust_ed() -> Command {
+    Command::cargo_bin("rust-ed").unwrap()
+}
+
+#[test]
+fn read_only_allows_print_number_and_search() {
+    let (_file, path) = input_file("alpha\nbeta\ngamma\n");
+
+    rust_ed()
+        .args(["-R", &path])
+        .write_stdin("1\n/gam/\n1p\n2n\nq\n")
+        .assert()
+        .success()
+        .stdout("17\nalpha\ngamma\nalpha\n2\tbeta\n");
+}
+
+#[test]
+fn read_only_rejects_delete_and_substitute() {
+    let (_file, path) = input_file("alpha\nbeta\n");
+
+    rust_ed()
+        .args(["--read-only", &path])
+        .write_stdin("H\n1d\ns/beta/BETA/\n,p\nw\nq\n")
+        .assert()
+        .stdout("11\n?\nRead-only mode\n?\nRead-only mode\nalpha\nbeta\n?\nRead-only mode\n");
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "alpha\nbeta\n");
+}
+
+#[test]
+fn version_reports_crate_version() {
+    rust_ed()
+        .arg("-V")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(format!("rust-ed {}", env!("CARGO_PKG_VERSION"))))
+        .stdout(predicates::str::contains("GNU ed 1.22.2"));
+}
+
+#[test]
+fn json_errors_replace_question_mark() {
+    let (_file, path) = input_file("alpha\n");
+
+    let output = rust_ed()
+        .arg(&path)
+        .env("RUST_ED_JSON_ERRORS", "1")
+        .write_stdin("5p\nq\n")
+        .output()
+        .unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let line = stderr.lines().last().unwrap();
+    let fields: Vec<&str> = line
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(',')
+        .collect();
+    assert_eq!(fields, ["\"error\":\"Invalid address\"", "\"code\":\"InvalidAddress\""]);
+}
+
+#[test]
+fn json_errors_off_by_default() {
+    let (_file, path) = input_file("alpha\n");
+
+    rust_ed()
+        .arg(&path)
+        .env_remove("RUST_ED_JSON_ERRORS")
+        .write_stdin("5p\nq\n")
+        .assert()
+        .stdout("6\n?\n")
+        .stderr("");
+}
+
+#[test]
+fn join_keeps_unterminated_binary_last_line() {
+    // A NUL byte makes the buffer binary, so the missing final newline is kept
+    // on write; GNU ed's joined line is a new node and gets the newline back
+    let (_file, path) = input_file("a\0b\nc\nd");
+
+    rust_ed()
+        .arg(&path)
+        .write_stdin("1,$j\nw\nq\n")
+        .assert()
+        .success();
+
+    assert_eq!(fs::read(&path).unwrap(), b"a\0bcd");
+}
+
+#[test]
+fn overlong_command_line_is_rejected() {
+    let (_file, path) = input_file("alpha\n");
+    let mut script = format!("s/alpha/{}/\n", "x".repeat(2 * 1024 * 1024));
+    script.push_str("h\n1p\nq\n");
+
+    rust_ed()
+        .arg(&path)
+        .write_stdin(script)
+        .assert()
+        .code(1)
+        .stdout("6\n?\nLine too long\nalpha\n");
+}
+
+#[test]
+fn verbose_status_line_stays_off_when_stderr_is_piped() {
+    // The H status line is for terminals only, so scripts see GNU ed's output
+    let (_file, path) = input_file("alpha\nbeta\n");
+
+    rust_ed()
+        .arg(&path)
+        .write_stdin("H\n5p\n1p\nq\n")
+        .assert()
+        .stdout("11\n?\nInvalid address\nalpha\n")
+        .stderr("");
+}
+
+#[test]
+fn verbose_binary_note_stays_off_when_stderr_is_piped() {
+    // The H "binary file" note is for terminals only, like the status line
+    let (_text, text_path) = input_file("alpha\n");
+    let (_binary, binary_path) = input_file("a\0b\n");
+
+    rust_ed()
+        .arg(&binary_path)
+        .write_stdin(format!("H\ne {}\nr {}\nQ\n", text_path, binary_path))
+        .assert()
+        .success()
+        .stdout("4\n6\n4\n")
+        .stderr("");
+}
+
+#[test]
+fn piped_commands_and_text_share_stdin() {
+    let dir = tempfile::tempdir().unwrap();
+
+    rust_ed()
+        .current_dir(dir.path())
+        .arg("out")
+        .write_stdin("a\nhi\n.\nw out\nq\n")
+        .assert()
+        .success()
+        .stdout("3\n")
+        .stderr("out: No such file or directory\n");
+
+    assert_eq!(fs::read_to_string(dir.path().join("out")).unwrap(), "hi\n");
+}
+
+// The panic hook is only compiled into debug builds
+#[cfg(debug_assertions)]
+#[test]
+fn internal_error_exits_with_status_3() {
+    rust_ed()
+        .env("RUST_ED_TEST_PANIC", "1")
+        .assert()
+        .code(3)
+        .stdout("")
+        .stderr(predicates::str::starts_with("ed: internal error: RUST_ED_TEST_PANIC is set"));
+}
+
+#[test]
+fn extra_file_names_are_reported() {
+    let (_first, first) = input_file("alpha\n");
+    let (_second, second) = input_file("beta\ngamma\n");
+
+    rust_ed()
+        .args(["--", &first, &second])
+        .write_stdin(",p\nq\n")
+        .assert()
+        .success()
+        .stdout("6\nalpha\n")
+        .stderr(format!("{}: Extra file name ignored\n", second));
+}
+
+#[test]
+fn trim_trailing_ws_strips_blanks_on_write() {
+    let (_file, path) = input_file("alpha  \nbeta\t\n gamma\n");
+
+    rust_ed()
+        .args(["--trim-trailing-ws", &path])
+        .write_stdin("w\n,l\nq\n")
+        .assert()
+        .success()
+        .stdout("21\n18\nalpha  $\nbeta\\t$\n gamma$\n");
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "alpha\nbeta\n gamma\n");
+}
+
+#[test]
+fn safe_write_warns_when_file_changed_on_disk() {
+    let (_file, path) = input_file("alpha\n");
+
+    rust_ed()
+        .args(["-s", "--safe-write", &path])
+        .write_stdin(format!("H\n!echo other > {}\nw\n", path))
+        .assert()
+        .code(1)
+        .stdout("?\nFile changed on disk\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "other\n");
+
+    // A second w after the warning writes anyway
+    rust_ed()
+        .args(["-s", "--safe-write", &path])
+        .write_stdin(format!("!echo changed > {}\nw\nw\n", path))
+        .assert()
+        .stdout("?\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "other\n");
+}
+
+#[test]
+fn changed_file_is_overwritten_without_safe_write() {
+    let (_file, path) = input_file("alpha\n");
+
+    rust_ed()
+        .args(["-s", &path])
+        .write_stdin(format!("!echo other > {}\nw\n", path))
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "alpha\n");
+}
+
+#[test]
+fn closed_stdout_does_not_abort_the_session() {
+    let content = "line\n".repeat(100_000);
+    let (_file, path) = input_file(&content);
+    let dir = tempfile::tempdir().unwrap();
+    let copy = dir.path().join("copy");
+    let status = dir.path().join("status");
+    let bin = assert_cmd::cargo::cargo_bin("rust-ed");
+
+    // head exits after one line; ed keeps writing into the closed pipe
+    let script = format!(
+        "printf ',p\\n=\\nw {}\\nq\\n' | {{ '{}' '{}'; echo $? > '{}'; }} | head -1",
+        copy.display(), bin.display(), path, status.display()
+    );
+    let output = std::process::Command::new("sh").arg("-c").arg(&script).output().unwrap();
+
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "500000\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    assert_eq!(fs::read_to_string(&status).unwrap(), "0\n");
+    assert_eq!(fs::read_to_string(&copy).unwrap(), content);
+}
+
+/// Create a FIFO in a fresh directory; the directory handle keeps it alive
+#[cfg(unix)]
+fn fifo() -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("fifo");
+    nix::unistd::mkfifo(&path, nix::sys::stat::Mode::S_IRWXU).unwrap();
+    (dir, path)
+}
+
+#[cfg(unix)]
+#[test]
+fn fifo_is_read_to_eof_by_r() {
+    let (_dir, path) = fifo();
+    let writer_path = path.clone();
+    let writer = std::thread::spawn(move || fs::write(writer_path, "one\ntwo\n").unwrap());
+
+    // GNU ed opens input "r+" first, which never sees EOF on a FIFO;
+    // rust-ed opens it read-only and reads until the writer closes
+    rust_ed()
+        .write_stdin(format!("r {}\n,p\nQ\n", path.display()))
+        .assert()
+        .success()
+        .stdout("8\none\ntwo\n");
+    writer.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn fifo_operand_is_loaded_at_startup() {
+    let (_dir, path) = fifo();
+    let writer_path = path.clone();
+    let writer = std::thread::spawn(move || fs::write(writer_path, "alpha\n").unwrap());
+
+    rust_ed()
+        .arg(&path)
+        .write_stdin("$p\nq\n")
+        .assert()
+        .success()
+        .stdout("6\nalpha\n");
+    writer.join().unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn fifo_without_writer_can_be_interrupted() {
+    use std::io::Write;
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let (_dir, path) = fifo();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rust-ed"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    child.stdin.as_mut().unwrap()
+        .write_all(format!("r {}\nQ\n", path.display()).as_bytes())
+        .unwrap();
+
+    // Give ed time to block opening the FIFO, then interrupt it
+    std::thread::sleep(Duration::from_millis(200));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > Duration::from_secs(5) {
+            child.kill().unwrap();
+            panic!("rust-ed kept waiting on the FIFO after SIGINT");
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn sigterm_saves_modified_buffer_to_ed_hup() {
+    use std::io::Write;
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rust-ed"))
+        .current_dir(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // Keep stdin open so ed is waiting for its next command
+    child.stdin.as_mut().unwrap().write_all(b"a\nunsaved\n.\n").unwrap();
+
+    std::thread::sleep(Duration::from_millis(200));
+    std::process::Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if start.elapsed() > Duration::from_secs(5) {
+            child.kill().unwrap();
+            panic!("rust-ed kept running after SIGTERM");
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(dir.path().join("ed.hup")).unwrap(), "unsaved\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn sighup_during_shell_command_saves_buffer_at_once() {
+    use std::io::Write;
+    use std::process::Stdio;
+    use std::time::{Duration, Instant};
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rust-ed"))
+        .current_dir(dir.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    // ed waits for the shell command when the hangup arrives
+    child.stdin.as_mut().unwrap().write_all(b"a\nunsaved\n.\n!sleep 30\n").unwrap();
+
+    std::thread::sleep(Duration::from_millis(200));
+    std::process::Command::new("kill")
+        .args(["-HUP", &child.id().to_string()])
+        .status()
+        .unwrap();
+
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if start.elapsed() > Duration::from_secs(5) {
+            child.kill().unwrap();
+            panic!("rust-ed waited for the shell command after SIGHUP");
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    assert!(status.success());
+    assert_eq!(fs::read_to_string(dir.path().join("ed.hup")).unwrap(), "unsaved\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn sigint_during_text_input_keeps_lines_read() {
+    use std::io::{Read, Write};
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    let (_file, path) = input_file("x\n");
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rust-ed"))
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(b"a\nline1\nline2\n").unwrap();
+
+    // Interrupt ed while it waits for more text, then look at the buffer
+    std::thread::sleep(Duration::from_millis(200));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    stdin.write_all(b"H\n.=\n,p\nu\n,p\nQ\n").unwrap();
+    drop(stdin);
+
+    let mut stdout = String::new();
+    child.stdout.take().unwrap().read_to_string(&mut stdout).unwrap();
+    // Like GNU ed, the lines typed before the interrupt stay, as one undoable change
+    assert_eq!(stdout, "2\n\n?\nInterrupt\n3\nx\nline1\nline2\nx\n");
+    assert!(child.wait().unwrap().success());
+}